@@ -0,0 +1,51 @@
+// how fast the sim ticks with a lot of bots on the map
+// no test harness, this just times a run and prints ticks per second
+//
+// what it got when bots went from a HashMap<u32, RefCell<BotState>> to flat per-field vecs,
+// the same bench run on the commit before and the commit that made the change, release build, one core
+// both came out with the same bots left, so it was the same run
+//   1500 bots:  ~370-420 ticks/s before, ~520-610 after
+//  20000 bots:  ~28 ticks/s before, ~45-59 after
+// later sim work (terrain, ink, decay, flow fields) costs some of that back, so compare runs of the same tree
+use std::time::Instant;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use clientwasm::sim::{Game, TickResult, Fx};
+use stratapaint_protocol::NetStep;
+
+const TICKRATIO: u32 = 4;
+const NETSTEPS: u32 = 50;
+
+// two teams spread over the whole map, so they fight as well as move
+fn setup(nbots: u32) -> Game {
+    let mut game = Game::empty(800, 800, 2, TICKRATIO, Fx::from_ratio(1, 10), 1);
+    let mut rng = XorShiftRng::seed_from_u64(1);
+    let max = Fx::from_int(800).to_bits();
+    for i in 0..nbots {
+        let x = Fx::from_bits(rng.gen_range(0, max));
+        let y = Fx::from_bits(rng.gen_range(0, max));
+        game.spawn_bot(x, y, (i % 2) as i32);
+    }
+    game
+}
+
+fn bench(nbots: u32) {
+    let mut game = setup(nbots);
+    let start = Instant::now();
+    for n in 0..NETSTEPS {
+        game.add_netstep(NetStep{n, updates: Vec::new()});
+        while !matches!(game.tick(), TickResult::Waiting) {}
+        game.prune(game.curtick);
+    }
+    let secs = start.elapsed().as_secs_f64();
+
+    let ticks = NETSTEPS * TICKRATIO;
+    let left = game.get_cur_tick().bots.len();
+    println!("{:>6} bots: {:>8.1} ticks/s ({} ticks in {:.2}s, {} bots left)", nbots, (ticks as f64) / secs, ticks, secs, left);
+}
+
+fn main() {
+    for n in [1500, 20000].iter() {
+        bench(*n);
+    }
+}
@@ -0,0 +1,147 @@
+// brushes turn a stroke into pixels, as a delta that goes out to the server
+// what comes back in the netstep is just the pixels, so every client paints exactly the same thing
+// all integer math anyway, positions are in map tiles with a pixel's center at its index
+// any brush can erase, by painting ERASE
+
+use std::collections::VecDeque;
+use stratapaint_protocol::PaintDelta;
+
+use crate::sim::GameMap;
+
+// clear paint away
+pub const ERASE: u32 = 0;
+
+// how far a fill can spread from where it started, so it stays under the paint area limit
+pub const FILLRAD: u32 = 127;
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum Brush {
+    Circle{x: u32, y: u32, rad: u32},
+    Line{points: Vec<(u32, u32)>, width: u32}, // through each point in turn, with round ends
+    Rect{x: u32, y: u32, w: u32, h: u32},
+    Fill{x: u32, y: u32}, // everything reachable without going through walls, up to FILLRAD away
+}
+
+impl Brush {
+    // the pixels this brush covers on the map in one color, cut off at the edges
+    // None if it doesn't cover anything
+    pub fn paint(&self, map: &GameMap, color: u32) -> Option<PaintDelta> {
+        match self {
+            Brush::Circle{x, y, rad} => circle(map, *x, *y, *rad, color),
+            Brush::Line{points, width} => line(map, points, *width, color),
+            Brush::Rect{x, y, w, h} => {
+                let (x0, y0, x1, y1) = clip(map, *x as i64, *y as i64, *x as i64 + *w as i64 - 1, *y as i64 + *h as i64 - 1)?;
+                let (w, h) = (x1 - x0 + 1, y1 - y0 + 1);
+                Some(PaintDelta::from_pixels(x0, y0, w, h, &vec![Some(color); (w * h) as usize]))
+            },
+            Brush::Fill{x, y} => fill(map, *x, *y, color),
+        }
+    }
+}
+
+// the part of a box on the map, inclusive
+fn clip(map: &GameMap, x0: i64, y0: i64, x1: i64, y1: i64) -> Option<(u32, u32, u32, u32)> {
+    let (x0, y0) = (x0.max(0), y0.max(0));
+    let (x1, y1) = (x1.min(map.w as i64 - 1), y1.min(map.h as i64 - 1));
+    if x0 > x1 || y0 > y1 {
+        return None;
+    }
+    Some((x0 as u32, y0 as u32, x1 as u32, y1 as u32))
+}
+
+// every pixel in a box that passes a test
+fn pixels<F: Fn(i64, i64) -> bool>(x0: u32, y0: u32, x1: u32, y1: u32, color: u32, f: F) -> Option<PaintDelta> {
+    let (w, h) = (x1 - x0 + 1, y1 - y0 + 1);
+    let mut px = Vec::with_capacity((w * h) as usize);
+    for y in y0..=y1 {
+        for x in x0..=x1 {
+            px.push(if f(x as i64, y as i64) { Some(color) } else { None });
+        }
+    }
+    let d = PaintDelta::from_pixels(x0, y0, w, h, &px);
+    if d.is_empty() {
+        None
+    } else {
+        Some(d)
+    }
+}
+
+fn circle(map: &GameMap, x: u32, y: u32, rad: u32, color: u32) -> Option<PaintDelta> {
+    let (cx, cy, r) = (x as i64, y as i64, rad as i64);
+    let (x0, y0, x1, y1) = clip(map, cx - r, cy - r, cx + r, cy + r)?;
+    pixels(x0, y0, x1, y1, color, |px, py| (px - cx) * (px - cx) + (py - cy) * (py - cy) <= r * r)
+}
+
+// pixels no more than width/2 from the line through all the points
+// distances are worked out doubled, so odd widths don't need fractions
+fn line(map: &GameMap, points: &[(u32, u32)], width: u32, color: u32) -> Option<PaintDelta> {
+    let first = points.first()?;
+    let w = width.max(1) as i64;
+    let pad = (w + 1) / 2;
+    let (mut x0, mut y0, mut x1, mut y1) = (first.0 as i64, first.1 as i64, first.0 as i64, first.1 as i64);
+    for (x, y) in points.iter() {
+        x0 = x0.min(*x as i64);
+        y0 = y0.min(*y as i64);
+        x1 = x1.max(*x as i64);
+        y1 = y1.max(*y as i64);
+    }
+    let (x0, y0, x1, y1) = clip(map, x0 - pad, y0 - pad, x1 + pad, y1 + pad)?;
+
+    // a lone point is just a dot
+    let segs: Vec<((i64, i64), (i64, i64))> = if points.len() == 1 {
+        vec![((first.0 as i64, first.1 as i64), (first.0 as i64, first.1 as i64))]
+    } else {
+        points.windows(2).map(|p| ((p[0].0 as i64, p[0].1 as i64), (p[1].0 as i64, p[1].1 as i64))).collect()
+    };
+    pixels(x0, y0, x1, y1, color, |px, py| segs.iter().any(|(a, b)| near_segment(*a, *b, (px, py), w)))
+}
+
+// if p is within w/2 of the segment from a to b
+fn near_segment(a: (i64, i64), b: (i64, i64), p: (i64, i64), w: i64) -> bool {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let (px, py) = (p.0 - a.0, p.1 - a.1);
+    let len2 = dx * dx + dy * dy;
+    let dot = px * dx + py * dy;
+
+    // (2 * dist)^2 <= w^2
+    if dot <= 0 || len2 == 0 {
+        return 4 * (px * px + py * py) <= w * w;
+    }
+    if dot >= len2 {
+        let (qx, qy) = (p.0 - b.0, p.1 - b.1);
+        return 4 * (qx * qx + qy * qy) <= w * w;
+    }
+    let cross = px * dy - py * dx;
+    4 * cross * cross <= w * w * len2
+}
+
+// flood out from a spot, four ways, stopping at anything bots can't go through
+fn fill(map: &GameMap, x: u32, y: u32, color: u32) -> Option<PaintDelta> {
+    if x >= map.w || y >= map.h || map.get_tile(x, y).blocks_move() {
+        return None;
+    }
+    let (x0, y0, x1, y1) = clip(map, x as i64 - FILLRAD as i64, y as i64 - FILLRAD as i64, x as i64 + FILLRAD as i64, y as i64 + FILLRAD as i64)?;
+    let (w, h) = (x1 - x0 + 1, y1 - y0 + 1);
+    let idx = |x: u32, y: u32| ((x - x0) + (y - y0) * w) as usize;
+
+    let mut px = vec![None; (w * h) as usize];
+    let mut todo = VecDeque::new();
+    px[idx(x, y)] = Some(color);
+    todo.push_back((x, y));
+    while let Some((cx, cy)) = todo.pop_front() {
+        let next = [
+            (cx > x0).then(|| (cx - 1, cy)),
+            (cx < x1).then(|| (cx + 1, cy)),
+            (cy > y0).then(|| (cx, cy - 1)),
+            (cy < y1).then(|| (cx, cy + 1)),
+        ];
+        for (nx, ny) in next.iter().flatten() {
+            let i = idx(*nx, *ny);
+            if px[i].is_none() && !map.get_tile(*nx, *ny).blocks_move() {
+                px[i] = Some(color);
+                todo.push_back((*nx, *ny));
+            }
+        }
+    }
+    Some(PaintDelta::from_pixels(x0, y0, w, h, &px))
+}
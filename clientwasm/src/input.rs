@@ -0,0 +1,91 @@
+// the local player's paint, from when it gets painted to when it comes back in a netstep
+// the server puts our strokes in the next netstep it sends, so nothing shows up in the sim until then
+// in the meantime they are drawn on an overlay over our team's layer, so painting feels right away
+// and come off the overlay once the netstep with them in it shows up
+
+use std::collections::VecDeque;
+use stratapaint_protocol::{NetStep, PaintDelta, PlayerId};
+
+use crate::brush::ERASE;
+use crate::sim::{GameMap, Px};
+
+// the server can drop strokes without saying so (before the game starts, after it ends), so they
+// are matched up with what comes back by what they paint, not by counting
+
+// erasing has nothing to draw, so pending erases go on as a haze over the paint they are taking off
+pub const ERASING: Px = Px{r: 0xff, g: 0xff, b: 0xff, a: 0x80};
+
+// netsteps to wait on a stroke before giving up on it, the server must have dropped it
+const PENDINGSTEPS: u32 = 16;
+
+struct Pending {
+    delta: PaintDelta,
+    after: u32, // the first netstep it could be in
+}
+
+pub struct Input {
+    pub overlay: GameMap, // strokes we have sent that haven't come back yet
+    pending: VecDeque<Pending>, // in the order they were sent, which is the order the server sends them back
+    nextstep: u32, // the next netstep we expect from the server
+}
+
+impl Input {
+    pub fn new(w: u32, h: u32) -> Input {
+        Input {
+            overlay: GameMap::new(w, h),
+            pending: VecDeque::new(),
+            nextstep: 0,
+        }
+    }
+
+    // a stroke that has been sent, and is waiting for a netstep
+    pub fn stroke(&mut self, delta: PaintDelta) {
+        draw(&mut self.overlay, &delta);
+        self.pending.push_back(Pending{delta, after: self.nextstep});
+    }
+
+    // a netstep showed up, so any of our strokes in it are in the sim now
+    pub fn landed(&mut self, st: &NetStep, player: PlayerId) {
+        self.nextstep = self.nextstep.max(st.n + 1);
+        let before = self.pending.len();
+        for u in st.updates.iter().filter(|u| u.player == player) {
+            // anything sent before it that didn't make it was dropped
+            if let Some(i) = self.pending.iter().position(|p| p.delta == u.delta) {
+                self.pending.drain(..=i);
+            }
+        }
+        while self.pending.front().is_some_and(|p| p.after + PENDINGSTEPS <= st.n) {
+            self.pending.pop_front();
+        }
+
+        // strokes can overlap, so just draw what is left over again
+        if self.pending.len() != before {
+            self.redraw();
+        }
+    }
+
+    // the server turned down a stroke, and there's no telling which, so give up on all of them
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.redraw();
+    }
+
+    fn redraw(&mut self) {
+        self.overlay.data.iter_mut().for_each(|p| *p = Px::CLEAR);
+        for p in self.pending.iter() {
+            draw(&mut self.overlay, &p.delta);
+        }
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+fn draw(map: &mut GameMap, d: &PaintDelta) {
+    d.for_each(|x, y, color| {
+        if x < map.w && y < map.h {
+            map.set(x, y, if color == ERASE { ERASING } else { Px::from(color) });
+        }
+    });
+}
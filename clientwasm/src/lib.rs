@@ -2,11 +2,20 @@ use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use std::cell::RefCell;
 use std::f64;
-use std::f32;
-use rand_xorshift::XorShiftRng;
-use rand::SeedableRng;
-use rand::Rng;
-use std::collections::HashMap;
+use stratapaint_protocol::{ServerMsg, ClientMsg, PaintUpdate};
+
+pub mod sim;
+use sim::{Game, GameMap, MapFile, TickResult, Px, Fx, BOTRAD, BASERAD};
+use sim::steer::Command;
+
+mod net;
+use net::Net;
+
+pub mod input;
+use input::Input;
+
+pub mod brush;
+use brush::Brush;
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
@@ -15,224 +24,17 @@ use std::collections::HashMap;
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
 // import in javascript symbols we want to use
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 extern {
     #[wasm_bindgen(js_namespace = console)]
     fn log(s: &str);
 }
 
-// bot state
-#[derive(Clone,Copy)]
-struct BotState {
-    id: u32,
-    health: f32,
-    x: f32, // these are in units of position on GameMap, not canvas. But we treat the canvas as px == GameMap unit, and just transform the canvas
-    y: f32,
-    vx: f32,
-    vy: f32,
-    team: i32,
-    cur_target: u32,
-}
-
-// bot constants
-const BOTRAD: f64 = 0.69;
-const BOTRANDMAXACC: f32 = 6.0;
-const BOTMAXVEL: f32 = 9.0;
-const BOTBOUNCEAMT: f32 = 0.42;
-const BOTCOLSZ: f32 = (BOTRAD as f32) * 0.15;
-
-#[derive(Clone)]
-struct LocationGroups {
-    groupw: u32,
-    grouph: u32,
-    vecs: Vec<Vec<u32>>,
-}
-
-// LocationGroup constants
-const GROUPSHIFT: u32 = 5;
-
-impl LocationGroups {
-    fn new(mapw: u32, maph: u32) -> LocationGroups {
-        let groupw = (mapw >> GROUPSHIFT)+1;
-        let grouph = (maph >> GROUPSHIFT)+1;
-        let mut vecs: Vec<Vec<u32>> = Vec::new();
-        for _ in 0..(groupw * grouph) {
-            vecs.push(Vec::new());
-        }
-        LocationGroups {
-            groupw,
-            grouph,
-            vecs,
-        }
-    }
-
-    fn add_bot(&mut self, id: u32, x: u32, y: u32) {
-        let xgroup = x >> GROUPSHIFT;
-        let ygroup = y >> GROUPSHIFT;
-
-        self.vecs[(xgroup + (ygroup * self.groupw)) as usize].push(id);
-    }
-
-    fn rm_bot(&mut self, id: u32, x: u32, y: u32) {
-        let xgroup = x >> GROUPSHIFT;
-        let ygroup = y >> GROUPSHIFT;
-
-        let v = &mut self.vecs[(xgroup + (ygroup * self.groupw)) as usize];
-
-        for i in 0..v.len() {
-            if v[i] == id {
-                v.remove(i);
-                return;
-            }
-        }
-
-        panic!("Didn't find bot where it was supposed to be in LocationGroups!");
-    }
-
-    fn move_bot(&mut self, id: u32, old_x: u32, old_y: u32, new_x: u32, new_y: u32) {
-        if ((old_x >> GROUPSHIFT) != (new_x >> GROUPSHIFT)) || ((old_y >> GROUPSHIFT) != (new_y >> GROUPSHIFT)) {
-            self.rm_bot(id, old_x, old_y);
-            self.add_bot(id, new_x, new_y);
-        }
-    }
-}
-
-// base state
-#[derive(Clone,Copy)]
-struct BaseState {
-    id: u32,
-    health: f32,
-    nextspawn: f32, // in game time seconds (based on ticks and tick_rate)
-    x: f32,
-    y: f32,
-    team: i32,
-}
-
-#[derive(PartialEq,Eq)]
-#[derive(Clone,Copy)]
-#[repr(packed)]
-pub struct Px {
-    r: u8,
-    g: u8,
-    b: u8,
-    a: u8,
-}
-
-impl Px {
-    const White: Px = Px{
-        r: 0xff,
-        g: 0xff,
-        b: 0xff,
-        a: 0xff,
-    };
-    const Black: Px = Px{
-        r: 0,
-        g: 0,
-        b: 0,
-        a: 0xff,
-    };
-    const Grey: Px = Px{
-        r: 0x80,
-        g: 0x80,
-        b: 0x80,
-        a: 0xff,
-    };
-    const Clear: Px = Px{
-        r: 0,
-        g: 0,
-        b: 0,
-        a: 0,
-    };
-}
-
-#[derive(Clone)]
-struct GameMap {
-    w: u32,
-    h: u32,
-    data: Box<[Px]>, // can be color, or for base map it is terrain info
-}
-
-impl GameMap {
-    fn new(w: u32, h: u32) -> GameMap {
-        GameMap{
-            w,
-            h,
-            data: (vec![Px::Clear; (w * h) as usize]).into_boxed_slice(),
-        }
-    }
-
-    fn set_tile(&mut self, x: u32, y: u32, tile: MapTiles) {
-        let p: Px = tile.into();
-        self.set(x, y, p);
-    }
-
-    fn set(&mut self, x: u32, y: u32, color: Px) {
-        if (x >= self.w) || (y >= self.h) {
-            panic!("Tried to access map past bounds");
-        }
-        let ind = (x + (y * self.w)) as usize;
-        self.data[ind] = color;
-    }
-
-    fn get_tile(&self, x: u32, y: u32) -> MapTiles {
-        self.get(x, y).into()
-    }
-
-    fn get(&self, x: u32, y: u32) -> Px {
-        if (x >= self.w) || (y >= self.h) {
-            panic!("Tried to access map past bounds");
-        }
-        let ind = (x + (y * self.w)) as usize;
-        self.data[ind]
-    }
-}
-
-#[derive(PartialEq,Eq)]
-enum MapTiles {
-    Wall,
-    Nothing,
-    Unk,
-}
-
-impl From<Px> for MapTiles {
-    fn from(orig: Px) -> Self {
-        match orig {
-            Px::White => MapTiles::Nothing,
-            Px::Black => MapTiles::Wall,
-            _ => MapTiles::Unk,
-        }
-    }
-}
-
-impl From<MapTiles> for Px {
-    fn from(orig: MapTiles) -> Self {
-        match orig {
-            MapTiles::Nothing => Px::White,
-            MapTiles::Wall => Px::Black,
-            MapTiles::Unk => Px::Clear,
-        }
-    }
-}
-
-// game tick structure
-// has to contain the current paint layers, and the current bot states, the base states
-// anything needed for the display needs to be here, because it can lag
-// and anything that can come from the "future" from the network: other player's paints
-#[derive(Clone)]
-struct GameTick {
-    tick: u32,
-    bases: Vec<BaseState>,
-    bots: HashMap<u32, RefCell<BotState>>, // Vector must always have lower bot id's ordered with indexes
-    teambotcount: Vec<u32>,
-    paints: Vec<GameMap>, // Maybe we want to have a DeltaMap option?
-}
-
-impl GameTick {
-    // cleans out things only needed for future tick processing
-    // and keeps the things needed for future drawing
-    fn cleanup(&mut self) {
-        self.paints.clear();
-    }
+// outside the browser (tests, the server) just print
+#[cfg(not(target_arch = "wasm32"))]
+fn log(s: &str) {
+    println!("{}", s);
 }
 
 struct DisplayInfo {
@@ -247,10 +49,23 @@ struct DisplayInfo {
     targetlag: f32, // how far behind we want to be in ticks
     ratio: f32, // current game seconds to go per real second for drawing to the screen, smooths lag and ticks
     tick: f32,   // where we are displaying in ticks
+    paints: Vec<GameMap>, // team paint layers as of the tick being shown, so the paint doesn't run ahead of the bots
+    painttick: u32, // which tick they are from
     
     //TODO add in particle effects tracking here for shooting
 }
 
+impl DisplayInfo {
+    // show a new game's current tick straight away, the error from before it has nothing to do with where we are now
+    fn jump(&mut self, game: &Game) {
+        self.tick = game.curtick as f32;
+        self.avgerr.clear();
+        self.avgerrsum = 0.0;
+        self.paints = game.get_cur_tick().paints.clone();
+        self.painttick = game.curtick;
+    }
+}
+
 // display constants and initial values
 const DIS_PK: f32 = 0.001;
 //const DIS_DK: f32 = 1.2;
@@ -260,354 +75,32 @@ const DIS_LAG: f32 = 3.9;
 const DIS_RMAX: f32 = 0.06;
 const DIS_EHIST: usize = 64;
 
-// game structure
-// has to contain a vec of GameTicks we have processed or are working on
-// also contains static game info, including the board layout
-struct Game {
-    bottree: LocationGroups, // collection of bots in curtick, used for avoiding and targeting nearby bots
-    states: Vec<GameTick>, // vector must always have the newest ticks (higher number) at lower indexes
-    tickratio: u32, // # of ticks before a netstep tick, doesn't change
-    tickstep: f32, // game seconds per tick, doesn't change (try to keep real seconds per tick similar to this)
-    curtick: u32, // next tick to process
-    map: GameMap, // the static map (walls and cover) below the changing paint layers
-    ctx: web_sys::CanvasRenderingContext2d, // the canvas ctx
-    canvas: web_sys::HtmlCanvasElement,
-    baseseed: u32,
-    objidcntr: u32,
-    dis: DisplayInfo,
-}
-
-// Game constants
-const STARTID: u32 = 1;
-const MAXCHECK: u32 = 9;
-
-impl Game {
-    fn add_bot(&mut self, tk: &mut GameTick, x: f32, y: f32, id: u32, team: i32) {
-        tk.bots.insert(id, RefCell::new(BotState {
-            id,
-            health: 100.0,
-            x,
-            y,
-            vx: 0.0,
-            vy: 0.0,
-            cur_target: 0,
-            team,
-        }));
-        tk.teambotcount[team as usize] += 1;
-
-        // add to tree
-        self.bottree.add_bot(id, x as u32, y as u32);
-    }
-
-
-    fn init_state(&mut self) {
-        // create the inital state and game map
-        if !self.states.is_empty() {
-            panic!("Tried to init state with non-empty states vector");
-        }
-        if self.curtick != 0 {
-            panic!("Tried to init state with nonzero current tick");
-        }
-
-        let mut tk = GameTick {
-            tick: 0,
-            bases: Vec::new(),
-            bots: HashMap::new(),
-            teambotcount: Vec::new(),
-            paints: Vec::new(),
-        };
-
-        // create per team layers
-        // for now only one team
-        for _ in 0..1 {
-            log("Adding Team");
-            tk.teambotcount.push(0);
-            tk.paints.push(GameMap::new(self.map.w, self.map.h));
-        }
-
-        // for now spawn a bunch of bots all across the map
-        let mut rng = XorShiftRng::seed_from_u64((self.baseseed) as u64);
-
-        // add a couple of random walls
-        for _ in 0..9 {
-            let x: u32 = rng.gen_range(0, self.map.w - 1);
-            let ystart: u32 = rng.gen_range(0,self.map.h - 30);
-            let yend: u32 = rng.gen_range(ystart+1, self.map.h);
-            for y in ystart..yend {
-                self.map.set_tile(x, y, MapTiles::Wall);
-            }
-        }
-
-        for _ in 0..9 {
-            let y: u32 = rng.gen_range(0, self.map.w - 1);
-            let xstart: u32 = rng.gen_range(0,self.map.w - 30);
-            let xend: u32 = rng.gen_range(xstart+1, self.map.w);
-            for x in xstart..xend {
-                self.map.set_tile(x, y, MapTiles::Wall);
-            }
-        }
-
-        for _ in 0..1200 {
-            self.add_bot(
-                &mut tk, ((self.map.w as f32)/2.0) + rng.gen_range(-(self.map.w as f32)/3.0, (self.map.w as f32)/3.0),
-                ((self.map.h as f32)/2.0) + rng.gen_range(-(self.map.h as f32)/3.0, (self.map.h as f32)/3.0),
-                self.objidcntr,
-                0,
-            );
-            self.objidcntr += 1;
-        }
-
-        // spawn a bunch in the middle
-
-        for _ in 0..300 {
-            self.add_bot(
-                &mut tk,
-                ((self.map.w as f32)/2.0) + rng.gen_range(-20.0, 20.0),
-                ((self.map.h as f32)/2.0) + rng.gen_range(-20.0, 20.0),
-                self.objidcntr,
-                0,
-            );
-            self.objidcntr += 1;
-        }
-
-        // play around with starting velocities
-        for b in tk.bots.values_mut() {
-            let b = &mut*b.borrow_mut();
-            b.vx = rng.gen_range(-BOTMAXVEL, BOTMAXVEL);
-            b.vy = rng.gen_range(-BOTMAXVEL, BOTMAXVEL);
-        }
-
-        log(&format!("starting out with {} bots", self.objidcntr)[..]);
-
-        self.states.push(tk);
-    }
-
-    fn tick(&mut self) {
-        //DEBUG
-        //let newtk: &mut GameTick = &mut self.states[0];
-
+// netsteps we run ahead on a guess when the server is late
+// the display never goes past the last confirmed tick, so a fixed up guess is always redone before it gets drawn
+const MAXPREDICT: u32 = 1;
 
-        let mut newtk: GameTick = self.get_cur_tick().clone();
-        let oldtick = newtk.tick; // save old tick so we can clean it at the end
-        newtk.tick += 1;
+// team colors (0xRRGGBB) for when init_game isn't given enough
+const TEAMCOLORS: [u32; 8] = [0xfa110e, 0x1e5cf0, 0x22b83a, 0xf0c20c, 0x9b30d9, 0x12c7c7, 0xf07c12, 0xf04bb4];
 
-        // get prng for this tick
-        let mut rng = XorShiftRng::seed_from_u64((self.baseseed + newtk.tick) as u64);
-        
-        for k in newtk.bots.keys() {
-            // add random accel to each bot
-            let amt: f32 = rng.gen_range(0.0, BOTRANDMAXACC) * self.tickstep;
-            let ang: f32 = rng.gen_range(0.0, f32::consts::PI * 2.0);
-
-            let xpart: f32 = ang.cos() * amt;
-            let ypart: f32 = ang.sin() * amt;
-
-            let bt = newtk.bots.get(k).unwrap();
-            let bt = &mut*bt.borrow_mut(); //TODO use Cell instead of refcell because it is copy
-            bt.vx += xpart;
-            bt.vy += ypart;
-
-            // query paint for force
-            //TODO
-
-            // loop through local bots
-            // just for avoidance for now
-
-            let xmin = bt.x - BOTCOLSZ;
-            let ymin = bt.y - BOTCOLSZ;
-            let xmax = bt.x + BOTCOLSZ;
-            let ymax = bt.y + BOTCOLSZ;
-
-            let xmin = if xmin <= 0.0 {
-                0
-            } else {
-                (xmin as u32) >> GROUPSHIFT
-            };
-            let ymin = if ymin <= 0.0 {
-                0
-            } else {
-                (ymin as u32) >> GROUPSHIFT
-            };
-            let xmax = (xmax as u32) >> GROUPSHIFT;
-            let xmax = if xmax >= self.bottree.groupw {
-                self.bottree.groupw
-            } else {
-                xmax+1
-            };
-            let ymax = (ymax as u32) >> GROUPSHIFT;
-            let ymax = if ymax >= self.bottree.grouph {
-                self.bottree.grouph
-            } else {
-                ymax+1
-            };
-
-            
-            // push apart close bots
-            for xg in xmin..xmax {
-                for yg in ymin..ymax {
-                    let mut numcheck = 0;
-                    for id2 in &self.bottree.vecs[(xg + (yg * self.bottree.groupw)) as usize] {
-                        // don't do the same work twice
-                        if id2 <= k {
-                            continue;
-                        }
-                        numcheck += 1;
-                        if numcheck > MAXCHECK {
-                            break;
-                        }
-
-                        let bt2 = newtk.bots.get(id2).unwrap();
-                        let bt2 = &mut*bt2.borrow_mut();
-
-                        // test boids
-
-                        
-                        let dx = bt.x - (*bt2).x;
-                        let dy = bt.y - (*bt2).y;
-
-                        if dx > 0.0 && dx < BOTCOLSZ {
-                            if bt.vx < 0.0 {
-                                bt.vx *= -BOTBOUNCEAMT;
-                            }
-                            if bt2.vx > 0.0 {
-                                bt2.vx *= -BOTBOUNCEAMT;
-                            }
-                        } else if dx < 0.0 && dx > -BOTCOLSZ {
-                            if bt.vx > 0.0 {
-                                bt.vx *= -BOTBOUNCEAMT;
-                            }
-                            if bt2.vx < 0.0 {
-                                bt2.vx *= -BOTBOUNCEAMT;
-                            }
-                        }
-
-                        if dy > 0.0 && dy < BOTCOLSZ {
-                            if bt.vy < 0.0 {
-                                bt.vy *= -BOTBOUNCEAMT;
-                            }
-                            if bt2.vy > 0.0 {
-                                bt2.vy *= -BOTBOUNCEAMT;
-                            }
-                        } else if dy < 0.0 && dy > -BOTCOLSZ {
-                            if bt.vy > 0.0 {
-                                bt.vy *= -BOTBOUNCEAMT;
-                            }
-                            if bt2.vy < 0.0 {
-                                bt2.vy *= -BOTBOUNCEAMT;
-                            }
-                        }
-                        
-                    }
-                }
-            }
 
-            // max out vel
-            // just use a simple P-Inf norm, instead of doing any sqrt for now
-            if bt.vx > BOTMAXVEL {
-                bt.vx = BOTMAXVEL;
-            } else if bt.vx < -BOTMAXVEL {
-                bt.vx = -BOTMAXVEL;
-            }
-            if bt.vy > BOTMAXVEL {
-                bt.vy = BOTMAXVEL;
-            } else if bt.vy < -BOTMAXVEL {
-                bt.vy = -BOTMAXVEL;
-            }
-
-            // step vel for each bot
-            let mut newx = bt.x + (bt.vx * self.tickstep);
-            let mut newy = bt.y + (bt.vy * self.tickstep);
-
-            // bounce off edge
-            // don't have to be as complicated as walls
-            // just don't move, and bounce velocity
-            let nix = newx as u32;
-            let niy = newy as u32;
-
-            let mut edgebounced = false;
-            if newx <= 0.0 || nix >= self.map.w {
-                newx = bt.x;
-                bt.vx *= -BOTBOUNCEAMT;
-                edgebounced = true;
-            }
-            if newy <= 0.0 || niy >= self.map.h {
-                newy = bt.y;
-                bt.vy *= -BOTBOUNCEAMT;
-                edgebounced = true;
-            }
-
-            // bounce off walls
-            // how to do this well?
-            let oix = bt.x as u32;
-            let oiy = bt.y as u32;
-            if (!edgebounced) && (self.map.get_tile(nix, niy) == MapTiles::Wall) && (self.map.get_tile(oix, oiy) != MapTiles::Wall) {
-                let mut xhit = self.map.get_tile(nix, oiy) == MapTiles::Wall;
-                let mut yhit = self.map.get_tile(oix, niy) == MapTiles::Wall;
-                if !xhit && !yhit {
-                    // bounce x and y respectively, but if we hit a corner, bounce both
-                    xhit = true;
-                    yhit = true;
-                }
-
-                if xhit{
-                    // bounce x
-                    newx = bt.x;
-                    bt.vx *= -BOTBOUNCEAMT;
-                }
-                if yhit{
-                    // bounce y
-                    newy = bt.y;
-                    bt.vy *= -BOTBOUNCEAMT;
-                }
-            }
-
-            // move the bot
-            self.bottree.move_bot(bt.id, bt.x as u32, bt.y as u32, newx as u32, newy as u32);
-            bt.x = newx;
-            bt.y = newy;
-        }
-
-        // clean old tick info not needed for drawing
-        let mut i = 0;
-        loop {
-            if i >= self.states.len() {
-                panic!("Could not find oldtick in states vector");
-            }
+// the game in the browser
+// the simulation plus everything needed to draw it on the canvas
+struct Client {
+    game: Game,
+    ctx: web_sys::CanvasRenderingContext2d, // the canvas ctx
+    dis: DisplayInfo,
+    teamcolors: Vec<String>, // css colors to draw each team's bots in
+    input: Input, // our paint that is on the way to the server and back
+}
 
-            let t = &mut self.states[i];
-            if t.tick == oldtick {
-                t.cleanup();
-                break;
-            }
+impl Client {
+    fn tick(&mut self) -> TickResult {
+        let res = self.game.tick();
 
-            i += 1;
-        };
-        
-        self.states.insert(0, newtk);
-        self.curtick += 1;
-
-        // do big cleanup
-        // clean up old ticks not needed anymore
-        let mut disp1 = self.dis.tick as u32;
-        if disp1 > self.curtick {
-            disp1 = self.curtick;
-        }
+        // clean up old ticks not needed for drawing anymore
+        self.game.prune(self.dis.tick as u32);
 
-        let mut i = 0;
-        while i < self.states.len() {
-            let mut isold: bool = false;
-            {
-                let t = &self.states[i];
-                if t.tick < disp1 {
-                    isold = true;
-                }
-            }
-            if isold {
-                self.states.remove(i);
-            } else {
-                i += 1;
-            }
-        }
+        res
     }
 
     fn draw(&mut self, dt: f32) {
@@ -616,21 +109,29 @@ impl Game {
         //https://www.hellorust.com/demos/canvas/index.html
         
         // get target ticks to lerp between
+        // only up to what the server has confirmed, a rollback could still change anything after that and make bots pop
+        let confirmed = self.game.confirmed_tick();
         let mut disp2 = self.dis.tick.ceil() as u32;
-        if disp2 > self.curtick {
-            self.dis.tick = self.curtick as f32;
-            disp2 = self.curtick;
+        if disp2 > confirmed {
+            self.dis.tick = confirmed as f32;
+            disp2 = confirmed;
         }
         let disp1 = self.dis.tick as u32;
         let lerpfac = self.dis.tick.fract();
 
+        // the paint to go with it, from the same tick as the bases
+        if disp2 != self.dis.painttick {
+            self.dis.paints = self.game.paints_at(disp2).expect("Could not step the paint back to the displayed tick");
+            self.dis.painttick = disp2;
+        }
+
         let mut i = 0;
         let tk2 = loop {
-            if i >= self.states.len() {
+            if i >= self.game.states.len() {
                 panic!("Could not find tk2 states vector");
             }
 
-            let t = &self.states[i];
+            let t = &self.game.states[i];
             if t.tick == disp2 {
                 break t;
             }
@@ -639,11 +140,11 @@ impl Game {
         };
         
         let tk1 = loop {
-            if i >= self.states.len() {
+            if i >= self.game.states.len() {
                 panic!("Could not find tk2 states vector");
             }
 
-            let t = &self.states[i];
+            let t = &self.game.states[i];
             if t.tick == disp1 {
                 break t;
             }
@@ -655,37 +156,73 @@ impl Game {
         //let tk1 = self.get_cur_tick();
         //let tk2 = self.get_cur_tick();
 
-        // draw bots
-        self.ctx.set_fill_style(&JsValue::from_str("#fa110e"));
-
-        // step through both ticks for bots
-        for (id, bt1) in tk1.bots.iter() {
-            if let Some(bt2) = tk2.bots.get(id) {
-                let bt1 = bt1.borrow();
-                let bt2 = bt2.borrow();
-                // lerp
-                let x: f32 = (lerpfac * (bt2.x - bt1.x)) + bt1.x;
-                let y: f32 = (lerpfac * (bt2.y - bt1.y)) + bt1.y;
-
-                self.ctx.begin_path();
-                self.ctx.arc(x as f64, y as f64, BOTRAD, 0.0, f64::consts::PI * 2.0).expect("Unable to draw bot");
-                self.ctx.fill();
-
-                //DEBUG
-                //self.ctx.begin_path();
-                //self.ctx.rect((x - (BOTCOLSZ/2.0)) as f64, (y - (BOTCOLSZ/2.0)) as f64, BOTCOLSZ as f64, BOTCOLSZ as f64);
-                //self.ctx.stroke();
-            } // else must be removed by next tick
-            //TODO explosion or something?
+        // draw bases
+        // they don't move, so no lerp
+        let side = (BASERAD * 2 + 1) as f64;
+        self.ctx.set_stroke_style_str("#000000");
+        for b in tk2.bases.iter() {
+            let x = (b.x.to_f32() as f64) - (side / 2.0);
+            let y = (b.y.to_f32() as f64) - (side / 2.0);
+            self.ctx.set_fill_style_str(&self.teamcolors[b.team as usize]);
+            self.ctx.fill_rect(x, y, side, side);
+            self.ctx.stroke_rect(x, y, side, side);
         }
 
-        // draw bases
-        //TODO
+        // draw bots, a team at a time so we only change the fill style once per team
+        for (team, color) in self.teamcolors.iter().enumerate() {
+            self.ctx.set_fill_style_str(color);
+
+            self.ctx.set_stroke_style_str(color);
+
+            // step through both ticks for bots
+            for bt1 in tk1.bots.iter() {
+                if bt1.team as usize != team {
+                    continue;
+                }
+                if let Some(bt2) = tk2.bots.get(bt1.id) {
+                    // lerp, floats are fine here since drawing doesn't feed back into the sim
+                    let x: f32 = (lerpfac * (bt2.x - bt1.x).to_f32()) + bt1.x.to_f32();
+                    let y: f32 = (lerpfac * (bt2.y - bt1.y).to_f32()) + bt1.y.to_f32();
+
+                    self.ctx.begin_path();
+                    self.ctx.arc(x as f64, y as f64, BOTRAD, 0.0, f64::consts::PI * 2.0).expect("Unable to draw bot");
+                    self.ctx.fill();
+
+                    //DEBUG
+                    //self.ctx.begin_path();
+                    //self.ctx.rect((x - (BOTCOLSZ/2.0)) as f64, (y - (BOTCOLSZ/2.0)) as f64, BOTCOLSZ as f64, BOTCOLSZ as f64);
+                    //self.ctx.stroke();
+                } else {
+                    // died by the next tick, a ring that grows out from where it was
+                    self.ctx.begin_path();
+                    self.ctx.arc(bt1.x.to_f32() as f64, bt1.y.to_f32() as f64, BOTRAD * (1.0 + 3.0 * lerpfac as f64), 0.0, f64::consts::PI * 2.0).expect("Unable to draw explosion");
+                    self.ctx.stroke();
+                }
+            }
+        }
 
         // Maps get drawn from the buffer directly
 
+        // how much paint we have left
+        if let Some(p) = NET.with(|nt| nt.borrow().as_ref().and_then(|net| net.player)) {
+            let ink = self.game.ink_left(self.game.player_team(p));
+            self.ctx.set_font("16px sans-serif");
+            self.ctx.set_fill_style_str("#000000");
+            self.ctx.fill_text(&format!("Ink {}%", (ink.to_f32() * 100.0) as u32), 20.0, 90.0).expect("Unable to draw ink");
+        }
+
+        if let Some(r) = &tk2.over {
+            let text = match r.winner {
+                Some(w) => format!("Team {} wins", w + 1),
+                None => "Nobody wins".to_string(),
+            };
+            self.ctx.set_font("48px sans-serif");
+            self.ctx.set_fill_style_str("#000000");
+            self.ctx.fill_text(&text, 20.0, 60.0).expect("Unable to draw game over");
+        }
+
         // adjust the ratio to even out
-        let err = ((self.curtick as f32) - self.dis.targetlag) - self.dis.tick;
+        let err = ((confirmed as f32) - self.dis.targetlag) - self.dis.tick;
         self.dis.avgerrsum += err;
         self.dis.avgerr.push(err);
         let elen = self.dis.avgerr.len();
@@ -695,50 +232,36 @@ impl Game {
 
         let err = self.dis.avgerrsum / (elen as f32);
         
-        self.dis.ratio = self.dis.pk * err;
-        if self.dis.ratio <= DIS_RMIN {
-            self.dis.ratio = DIS_RMIN;
-        } else if self.dis.ratio >= DIS_RMAX {
-            self.dis.ratio = DIS_RMAX;
-        }
+        self.dis.ratio = (self.dis.pk * err).clamp(DIS_RMIN, DIS_RMAX);
 
         //DEBUG
-        if disp1 == self.curtick {
+        if disp1 == confirmed {
             log(&format!("WARNING, RAILED {}", disp1)[..])
         }
 
         // move our displayed tick
         self.dis.tick += dt * self.dis.ratio;
     }
-
-    fn get_cur_tick(&self) -> &GameTick {
-        let mut i = 0;
-        loop {
-            if i >= self.states.len() {
-                panic!("Could not find curtick in states vector");
-            }
-
-            let t = &self.states[i];
-            if t.tick == self.curtick {
-                return t;
-            }
-
-            i += 1;
-        };
-    }
 }
 
 thread_local!(
-    static GAME: RefCell<Option<Game>> = RefCell::new(None);
+    static GAME: RefCell<Option<Client>> = const { RefCell::new(None) };
+    static NET: RefCell<Option<Net>> = const { RefCell::new(None) };
 );
 
+// js passes all the setup in one go
+#[allow(clippy::too_many_arguments)]
 #[wasm_bindgen]
 pub fn init_game(can_id: &str,
     mapw: u32,
     maph: u32,
+    nteams: u32,
+    team_colors: Vec<u32>, // 0xRRGGBB for each team, any missing get a default
     tick_ratio: u32,    // number of ticks per netstep
-    tick_step: f32,     // milliseconds per tick (ish, can be affected by netstep lag or computation lag)
+    tick_step: u32,     // milliseconds per tick (ish, can be affected by netstep lag or computation lag)
     seed: u32,
+    map: Vec<u8>,       // png map file, or mapw x maph of rgba pixels (like ImageData.data), empty to make one up from the seed
+    decay: u32,         // paint strength (out of 255) lost per game second, 0 to keep paint forever
 ) {
     // do init stuff
     // setup console panics
@@ -765,19 +288,32 @@ pub fn init_game(can_id: &str,
         .dyn_into::<web_sys::CanvasRenderingContext2d>()
         .unwrap();
 
-    let tickstep = tick_step / 1000.0; // milliseconds to seconds
+    let tickstep = Fx::from_ratio(tick_step as i32, 1000); // milliseconds to seconds
     // tick_step (ms / tick)
     // dispratio (ticks / ms) = 1/tick_step 
-    let dispratio = 1.0 / tick_step;
+    let dispratio = 1.0 / (tick_step as f32);
+
+    let teamcolors = (0..(nteams as usize)).map(|t| {
+        let c = team_colors.get(t).copied().unwrap_or(TEAMCOLORS[t % TEAMCOLORS.len()]);
+        format!("#{:06x}", c & 0xffffff)
+    }).collect();
+
+    let mut game = if map.is_empty() {
+        Game::new(mapw, maph, nteams, tick_ratio, tickstep, seed)
+    } else {
+        let mf = MapFile::load(mapw, maph, &map).unwrap_or_else(|e| panic!("Couldn't load the map: {}", e));
+        Game::with_map(&mf, nteams, tick_ratio, tickstep, seed).unwrap_or_else(|e| panic!("Couldn't use the map: {}", e))
+    };
+    game.maxpredict = MAXPREDICT;
+    game.decay = decay;
+    let input = Input::new(game.map.w, game.map.h);
+    let paints = game.get_cur_tick().paints.clone();
 
     GAME.with(|g| {
         let g = &mut *g.borrow_mut();
-        *g = Some(Game {
-            states: Vec::new(),
-            bottree: LocationGroups::new(mapw, maph),
-            tickratio: tick_ratio,
-            tickstep,
-            curtick: 0,
+        *g = Some(Client {
+            game,
+            ctx,
             dis: DisplayInfo {
                 pk: DIS_PK,
                 //dk: DIS_DK,
@@ -787,50 +323,318 @@ pub fn init_game(can_id: &str,
                 //ierr: 0.0,
                 ratio: dispratio,
                 tick: 0.0,
+                paints,
+                painttick: 0,
                 targetlag: DIS_LAG,
                 avgerr: Vec::new(),
                 avgerrsum: 0.0,
             },
-            map: GameMap::new(mapw, maph),
-            ctx,
-            canvas,
-            baseseed: seed,
-            objidcntr: STARTID,
+            teamcolors,
+            input,
         });
-
-        if let Some(game) = &mut *g {
-            game.init_state();
-        }
     });
 }
 
 #[wasm_bindgen]
 pub fn draw(dt: f32) {
     GAME.with(|g| {
-        if let Some(game) = &mut *g.borrow_mut() {
-            game.draw(dt);
+        if let Some(c) = &mut *g.borrow_mut() {
+            c.draw(dt);
         }
     });
 }
 
+// returns false if we are waiting on the server for the next netstep
 #[wasm_bindgen]
-pub fn tick() {
-    GAME.with(|g| {
-        if let Some(game) = &mut *g.borrow_mut() {
-            game.tick();
+pub fn tick() -> bool {
+    let res = GAME.with(|g| {
+        match &mut *g.borrow_mut() {
+            Some(c) => c.tick(),
+            None => TickResult::Waiting,
+        }
+    });
+
+    match res {
+        TickResult::Waiting => false,
+        TickResult::Ticked => true,
+        // acked once the real netstep shows up
+        TickResult::Predicted(_) => true,
+        TickResult::NetStep(n) => {
+            // the ack carries a hash of the tick that started the netstep, so the server can check we agree
+            let hash = GAME.with(|g| {
+                g.borrow().as_ref().map_or(0, |c| c.game.get_cur_tick().hash())
+            });
+            NET.with(|nt| {
+                if let Some(net) = &mut *nt.borrow_mut() {
+                    net.send(ClientMsg::Ack(n, hash));
+                }
+            });
+            true
+        },
+    }
+}
+
+// start talking to the server, the game gets set up once we get the game info
+#[wasm_bindgen]
+pub fn connect(can_id: &str, game: &str) {
+    NET.with(|nt| {
+        let mut net = Net::new(can_id);
+        net.send(ClientMsg::Join(game.to_string()));
+        *nt.borrow_mut() = Some(net);
+    });
+}
+
+// handle a message from the server
+// returns true if this started a new game, so js can set up the buffers and start ticking
+#[wasm_bindgen]
+pub fn net_recv(msg: &[u8]) -> bool {
+    let msg = match ServerMsg::decode(msg) {
+        Ok(m) => m,
+        Err(e) => {
+            log(&format!("Got bad message from server: {}", e)[..]);
+            return false;
+        }
+    };
+
+    match msg {
+        ServerMsg::Pong => false,
+        ServerMsg::Error(e) => {
+            log(&format!("Got error from server: {}", e)[..]);
+            GAME.with(|g| {
+                if let Some(c) = &mut *g.borrow_mut() {
+                    c.input.clear();
+                }
+            });
+            false
+        },
+        ServerMsg::Info(info) => {
+            log(&format!("{}/{} players ready", info.nready, info.nplayers)[..]);
+
+            // only the first game info sets us up, after that it is just ready counts
+            let can_id = NET.with(|nt| {
+                match &mut *nt.borrow_mut() {
+                    Some(net) if net.player.is_none() => {
+                        net.player = Some(info.player);
+                        net.send(ClientMsg::Ready);
+                        Some(net.can_id.clone())
+                    },
+                    _ => None,
+                }
+            });
+
+            match can_id {
+                Some(can_id) => {
+                    init_game(&can_id, info.mapw, info.maph, info.nteams, Vec::new(), info.tickratio, info.tickstep, info.seed, info.map, info.decay);
+                    true
+                },
+                None => false,
+            }
+        },
+        ServerMsg::Step(st) => {
+            let n = st.n;
+            let player = NET.with(|nt| nt.borrow().as_ref().and_then(|net| net.player));
+            let hash = GAME.with(|g| {
+                match &mut *g.borrow_mut() {
+                    Some(c) => {
+                        if let Some(p) = player {
+                            c.input.landed(&st, p);
+                        }
+                        c.game.add_netstep(st)
+                    },
+                    None => None,
+                }
+            });
+
+            // we had already guessed past it, so there is no tick left to ack it from
+            if let Some(hash) = hash {
+                NET.with(|nt| {
+                    if let Some(net) = &mut *nt.borrow_mut() {
+                        net.send(ClientMsg::Ack(n, hash));
+                    }
+                });
+            }
+            false
+        },
+        ServerMsg::Resync(state) => {
+            let mut game = match Game::from_snapshot(&state) {
+                Ok(g) => g,
+                Err(e) => {
+                    log(&format!("Got bad game state from server: {}", e)[..]);
+                    return false;
+                }
+            };
+            game.maxpredict = MAXPREDICT;
+
+            GAME.with(|g| {
+                if let Some(c) = &mut *g.borrow_mut() {
+                    log(&format!("Resynced at tick {}", game.curtick)[..]);
+                    // jump the display to the new state, there is nothing to draw before it
+                    c.dis.jump(&game);
+                    // whatever of our paint made it in is in the new state, and the rest isn't coming back
+                    c.input.clear();
+                    c.game = game;
+                }
+            });
+            false
+        },
+        ServerMsg::Desync(r) => {
+            log(&format!("Desync at netstep {} (tick {}), server had {:016x}", r.n, r.tick, r.expected)[..]);
+            for (p, h) in r.hashes.iter() {
+                log(&format!("  player {} had {:016x}", p, h)[..]);
+            }
+
+            // if we are one of the ones that went wrong, start over from the server's state
+            NET.with(|nt| {
+                if let Some(net) = &mut *nt.borrow_mut() {
+                    let ours = r.hashes.iter().find(|(p, _)| Some(*p) == net.player);
+                    if matches!(ours, Some((_, h)) if *h != r.expected) {
+                        net.send(ClientMsg::WantResync);
+                    }
+                }
+            });
+            false
+        },
+        ServerMsg::GameOver(r) => {
+            // the sim works this out on its own, this is just so we know the server agrees
+            match r.winner {
+                Some(w) => log(&format!("Game over at tick {}, team {} wins", r.tick, w)[..]),
+                None => log(&format!("Game over at tick {}, nobody wins", r.tick)[..]),
+            }
+            for (i, t) in r.teams.iter().enumerate() {
+                log(&format!("  team {}: {} bots, {} bases, {} spawned, {} lost", i, t.bots, t.bases, t.spawned, t.lost)[..]);
+            }
+            false
+        },
+    }
+}
+
+// ask the server for the whole game state, if we think we have gone wrong
+#[wasm_bindgen]
+pub fn request_resync() {
+    NET.with(|nt| {
+        if let Some(net) = &mut *nt.borrow_mut() {
+            net.send(ClientMsg::WantResync);
         }
     });
 }
 
+// next message to send to the server, if any
+#[wasm_bindgen]
+pub fn net_send() -> Option<Vec<u8>> {
+    NET.with(|nt| {
+        match &mut *nt.borrow_mut() {
+            Some(net) => net.next_outgoing(),
+            None => None,
+        }
+    })
+}
+
+// paint kinds for the paint functions
+const PAINT_ATTRACT: u32 = 0;
+const PAINT_REPEL: u32 = 1;
+const PAINT_HOLD: u32 = 2;
+const PAINT_DIR: u32 = 3; // dir is which way, in 1/256 turns clockwise from +x
+const PAINT_ERASE: u32 = 4;
+
+// paint on our team's layer
+// it shows up on the paint buf right away, and goes out to the server for the next netstep it sends
+// returns false if we aren't in a game, or the paint isn't any good
+fn send_brush(b: Brush, kind: u32, dir: u32) -> bool {
+    let color = match kind {
+        PAINT_ATTRACT => u32::from(Command::Attract.to_px(0xff)),
+        PAINT_REPEL => u32::from(Command::Repel.to_px(0xff)),
+        PAINT_HOLD => u32::from(Command::Hold.to_px(0xff)),
+        PAINT_DIR => u32::from(Command::Dir(dir as u8).to_px(0xff)),
+        PAINT_ERASE => brush::ERASE,
+        _ => return false,
+    };
+    let player = match NET.with(|nt| nt.borrow().as_ref().and_then(|net| net.player)) {
+        Some(p) => p,
+        None => return false,
+    };
+
+    let delta = GAME.with(|g| {
+        let mut g = g.borrow_mut();
+        let c = match &mut *g {
+            Some(c) => c,
+            None => return None,
+        };
+        let d = b.paint(&c.game.map, color)?;
+        if let Err(e) = c.game.check_paint(&d) {
+            log(&format!("Not sending paint: {}", e)[..]);
+            return None;
+        }
+        // it would only get dropped, but the sim is the one that decides
+        let team = c.game.player_team(player);
+        if sim::paint_cost(&d) > c.game.get_cur_tick().ink[team] as u64 {
+            log("Not sending paint: not enough ink");
+            return None;
+        }
+        c.input.stroke(d.clone());
+        Some(d)
+    });
+
+    match delta {
+        Some(delta) => {
+            NET.with(|nt| {
+                if let Some(net) = &mut *nt.borrow_mut() {
+                    net.send(ClientMsg::Paint(PaintUpdate{player, delta}));
+                }
+            });
+            true
+        },
+        None => false,
+    }
+}
+
+#[wasm_bindgen]
+pub fn paint_stroke(x: u32, y: u32, rad: u32, kind: u32, dir: u32) -> bool {
+    send_brush(Brush::Circle{x, y, rad}, kind, dir)
+}
+
+// points are x, y pairs
+#[wasm_bindgen]
+pub fn paint_line(points: Vec<u32>, width: u32, kind: u32, dir: u32) -> bool {
+    let points = points.chunks_exact(2).map(|p| (p[0], p[1])).collect();
+    send_brush(Brush::Line{points, width}, kind, dir)
+}
+
+#[wasm_bindgen]
+pub fn paint_rect(x: u32, y: u32, w: u32, h: u32, kind: u32, dir: u32) -> bool {
+    send_brush(Brush::Rect{x, y, w, h}, kind, dir)
+}
+
+// out to the walls around it
+#[wasm_bindgen]
+pub fn paint_fill(x: u32, y: u32, kind: u32, dir: u32) -> bool {
+    send_brush(Brush::Fill{x, y}, kind, dir)
+}
+
+// the team our paint goes on, -1 if we aren't in a game yet
+#[wasm_bindgen]
+pub fn local_team() -> i32 {
+    let player = NET.with(|nt| nt.borrow().as_ref().and_then(|net| net.player));
+    GAME.with(|g| {
+        match (&*g.borrow(), player) {
+            (Some(c), Some(p)) => c.game.player_team(p) as i32,
+            _ => -1,
+        }
+    })
+}
+
+// -1 for the map, or a team's paint layer as of the tick being drawn
+// paint layers get swapped out as the game goes, so get the buffer again every time it is drawn
 #[wasm_bindgen]
 pub fn get_buf(team: i32) -> *const Px {
     let mut retbuf = std::ptr::null();
     GAME.with(|g| {
-        if let Some(game) = &mut *g.borrow_mut() {
+        if let Some(c) = &*g.borrow() {
             if team == -1 {
-                let buf = &game.map.data;
-                let b = &*buf;
-                retbuf = b.as_ptr();
+                retbuf = c.game.map.data.as_ptr();
+            } else if team >= 0 {
+                if let Some(p) = c.dis.paints.get(team as usize) {
+                    retbuf = p.data.as_ptr();
+                }
             }
         }
     });
@@ -838,11 +642,41 @@ pub fn get_buf(team: i32) -> *const Px {
     retbuf
 }
 
+// our paint that hasn't come back from the server yet, to draw over our team's layer
+#[wasm_bindgen]
+pub fn get_paint_buf() -> *const Px {
+    GAME.with(|g| {
+        g.borrow().as_ref().map_or(std::ptr::null(), |c| c.input.overlay.data.as_ptr())
+    })
+}
+
+#[wasm_bindgen]
+pub fn game_width() -> u32 {
+    GAME.with(|g| {
+        g.borrow().as_ref().map_or(0, |c| c.game.map.w)
+    })
+}
+
+#[wasm_bindgen]
+pub fn game_height() -> u32 {
+    GAME.with(|g| {
+        g.borrow().as_ref().map_or(0, |c| c.game.map.h)
+    })
+}
+
+// milliseconds per tick
+#[wasm_bindgen]
+pub fn game_tick_step() -> f32 {
+    GAME.with(|g| {
+        g.borrow().as_ref().map_or(0.0, |c| c.game.tickstep.to_f32() * 1000.0)
+    })
+}
+
 #[wasm_bindgen]
 pub fn adj_dis(pk: f32, targetlag: f32) {
     GAME.with(|g| {
-        if let Some(game) = &mut *g.borrow_mut() {
-            let di = &mut game.dis;
+        if let Some(c) = &mut *g.borrow_mut() {
+            let di = &mut c.dis;
             di.pk = pk;
             //di.dk = dk;
             //di.ik = ik;
@@ -853,11 +687,12 @@ pub fn adj_dis(pk: f32, targetlag: f32) {
 
 //API
 
-// get_paint_buf()
-
-// setpaint(netstep #) (indicates shared memory area given by get_paint_buf is updated in the given bounds)
+// get_buf(team) and get_paint_buf() give pointers into wasm memory, for ImageData on the js side
 // see https://rustwasm.github.io/book/game-of-life/implementing.html for how to access wasm memory from js
 
+// paint_stroke(x, y, rad, kind, dir), paint_line, paint_rect and paint_fill paint with a brush
+// and send it off to be in a future netstep
+
 // tick()
 
 // draw(dt)
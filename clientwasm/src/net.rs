@@ -0,0 +1,31 @@
+// the client end of the websocket protocol
+// js owns the actual websocket, it passes us every message it gets
+// and sends anything we have queued up
+// the messages themselves are in stratapaint_protocol, shared with the server
+
+use std::collections::VecDeque;
+use stratapaint_protocol::{ClientMsg, PlayerId};
+
+pub struct Net {
+    pub can_id: String,
+    pub player: Option<PlayerId>, // set once we have game info and have sent ready
+    outgoing: VecDeque<Vec<u8>>,
+}
+
+impl Net {
+    pub fn new(can_id: &str) -> Net {
+        Net {
+            can_id: can_id.to_string(),
+            player: None,
+            outgoing: VecDeque::new(),
+        }
+    }
+
+    pub fn send(&mut self, msg: ClientMsg) {
+        self.outgoing.push_back(msg.encode());
+    }
+
+    pub fn next_outgoing(&mut self) -> Option<Vec<u8>> {
+        self.outgoing.pop_front()
+    }
+}
@@ -0,0 +1,118 @@
+// every bot in a tick, stored a field at a time
+// the whole thing gets copied into each new tick, and flat vecs of plain numbers are cheap to copy
+// bots are kept in id order, so every client goes through them the same way
+// and a bot can be found by id with a binary search
+
+use super::{BotState, Fx};
+
+#[derive(Clone,Default)]
+pub struct Bots {
+    pub(super) id: Vec<u32>,
+    pub(super) health: Vec<Fx>,
+    pub(super) x: Vec<Fx>,
+    pub(super) y: Vec<Fx>,
+    pub(super) vx: Vec<Fx>,
+    pub(super) vy: Vec<Fx>,
+    pub(super) team: Vec<i32>,
+    pub(super) cur_target: Vec<u32>,
+}
+
+impl Bots {
+    pub fn new() -> Bots {
+        Bots::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.id.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.id.is_empty()
+    }
+
+    // where a bot is, indexes go stale once bots are added or removed
+    pub fn index(&self, id: u32) -> Option<usize> {
+        self.id.binary_search(&id).ok()
+    }
+
+    pub fn contains(&self, id: u32) -> bool {
+        self.index(id).is_some()
+    }
+
+    pub fn at(&self, i: usize) -> BotState {
+        BotState {
+            id: self.id[i],
+            health: self.health[i],
+            x: self.x[i],
+            y: self.y[i],
+            vx: self.vx[i],
+            vy: self.vy[i],
+            team: self.team[i],
+            cur_target: self.cur_target[i],
+        }
+    }
+
+    pub fn get(&self, id: u32) -> Option<BotState> {
+        self.index(id).map(|i| self.at(i))
+    }
+
+    // write a bot back, it has to stay the same bot
+    pub fn set(&mut self, i: usize, b: &BotState) {
+        if self.id[i] != b.id {
+            panic!("Tried to write a bot over a different one");
+        }
+        self.health[i] = b.health;
+        self.x[i] = b.x;
+        self.y[i] = b.y;
+        self.vx[i] = b.vx;
+        self.vy[i] = b.vy;
+        self.team[i] = b.team;
+        self.cur_target[i] = b.cur_target;
+    }
+
+    // new ids are always the highest so far, so this is usually just a push
+    pub fn insert(&mut self, b: BotState) {
+        let i = match self.id.binary_search(&b.id) {
+            Ok(_) => panic!("Tried to add a bot with an id already in use"),
+            Err(i) => i,
+        };
+        self.id.insert(i, b.id);
+        self.health.insert(i, b.health);
+        self.x.insert(i, b.x);
+        self.y.insert(i, b.y);
+        self.vx.insert(i, b.vx);
+        self.vy.insert(i, b.vy);
+        self.team.insert(i, b.team);
+        self.cur_target.insert(i, b.cur_target);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = BotState> + '_ {
+        (0..self.len()).map(move |i| self.at(i))
+    }
+
+    // keep the bots f says to, in one pass so it stays cheap with lots of bots dying
+    pub fn retain<F>(&mut self, mut f: F)
+        where F: FnMut(&BotState) -> bool
+    {
+        let mut j = 0;
+        for i in 0..self.len() {
+            let b = self.at(i);
+            if !f(&b) {
+                continue;
+            }
+            if i != j {
+                self.id[j] = b.id;
+                self.set(j, &b);
+            }
+            j += 1;
+        }
+        self.id.truncate(j);
+        self.health.truncate(j);
+        self.x.truncate(j);
+        self.y.truncate(j);
+        self.vx.truncate(j);
+        self.vy.truncate(j);
+        self.team.truncate(j);
+        self.cur_target.truncate(j);
+    }
+}
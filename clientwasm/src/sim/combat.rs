@@ -0,0 +1,119 @@
+// bots fighting each other, and enemy bases
+// bots are gone through in id order, ties are broken by id, and damage lands all at once
+// so every client comes up with the same fights
+
+use super::{Game, GameTick, GameMap, MapTiles, BotState, BaseState, Fx, BASERAD};
+
+// combat constants
+pub const BOTRANGE: Fx = Fx::from_int(3); // in map tiles, using the same P-Inf norm as everything else
+const BOTDPS: Fx = Fx::from_int(20); // damage per game second
+const COVERDMG: Fx = Fx::from_ratio(1, 2); // part of the damage taken by bots in cover
+const RETARGETTICKS: u32 = 4; // bots without a target only look for one every this many ticks, it is the slow part
+
+fn dist(a: &BotState, b: &BotState) -> Fx {
+    (a.x - b.x).abs().max((a.y - b.y).abs())
+}
+
+// to the edge of the base, zero if on it
+fn base_dist(a: &BotState, b: &BaseState) -> Fx {
+    let d = (a.x - b.x).abs().max((a.y - b.y).abs()) - Fx::from_int(BASERAD as i32);
+    d.max(Fx::ZERO)
+}
+
+// bots shoot at the closest part of a base they can see, so it has to be on the map
+fn can_see_base(map: &GameMap, a: &BotState, b: &BaseState) -> bool {
+    let rad = Fx::from_int(BASERAD as i32);
+    let maxx = Fx::from_int(map.w as i32) - Fx::from_bits(1);
+    let maxy = Fx::from_int(map.h as i32) - Fx::from_bits(1);
+    let x = a.x.clamp(b.x - rad, b.x + rad).clamp(Fx::ZERO, maxx);
+    let y = a.y.clamp(b.y - rad, b.y + rad).clamp(Fx::ZERO, maxy);
+    map.clear_sight(a.x, a.y, x, y)
+}
+
+// what a bot can shoot, bots and bases share the same ids
+fn valid_target(map: &GameMap, tk: &GameTick, bt: &BotState, id: u32) -> bool {
+    if id == bt.id {
+        return false;
+    }
+    if let Some(t) = tk.bots.get(id) {
+        return t.team != bt.team && dist(bt, &t) <= BOTRANGE && map.clear_sight(bt.x, bt.y, t.x, t.y);
+    }
+    tk.bases.iter().any(|b| b.id == id && b.team != bt.team && base_dist(bt, b) <= BOTRANGE && can_see_base(map, bt, b))
+}
+
+impl Game {
+    // closest enemy bot in range and sight, then closest enemy base, or 0 for none
+    fn closest_enemy(&self, tk: &GameTick, bt: &BotState) -> u32 {
+        let map = &self.map;
+        let seen = |o: &BotState| o.team != bt.team && map.clear_sight(bt.x, bt.y, o.x, o.y);
+        if let Some(id) = self.bottree.nearest(&tk.bots, bt.x, bt.y, BOTRANGE, 1, seen).first() {
+            return *id;
+        }
+
+        // bases only once there are no bots to fight
+        let mut best: Option<(Fx, u32)> = None;
+        for b in tk.bases.iter() {
+            if b.team == bt.team {
+                continue;
+            }
+            let d = base_dist(bt, b);
+            if d <= BOTRANGE && best.is_none_or(|o| (d, b.id) < o) && can_see_base(map, bt, b) {
+                best = Some((d, b.id));
+            }
+        }
+
+        best.map_or(0, |(_, id)| id)
+    }
+
+    // pick targets, shoot, and take out the dead
+    // runs after everything has moved for the tick
+    pub(super) fn fight(&mut self, tk: &mut GameTick) {
+        // keep shooting the same thing while it is still around and in range
+        // otherwise look for a new one when it is our turn, spread out by id so it isn't all on one tick
+        for i in 0..tk.bots.len() {
+            let bt = tk.bots.at(i);
+            if valid_target(&self.map, tk, &bt, bt.cur_target) {
+                continue;
+            }
+            let t = if (tk.tick + bt.id).is_multiple_of(RETARGETTICKS) {
+                self.closest_enemy(tk, &bt)
+            } else {
+                0
+            };
+            tk.bots.cur_target[i] = t;
+        }
+
+        // everyone shoots at once, so it doesn't matter who goes first
+        let dmg = BOTDPS * self.tickstep;
+        let hits: Vec<u32> = tk.bots.cur_target.iter().copied().filter(|t| *t != 0).collect();
+        for t in hits {
+            match tk.bots.index(t) {
+                Some(i) => {
+                    let cover = self.map.get_tile(tk.bots.x[i].tile(), tk.bots.y[i].tile()) == MapTiles::Cover;
+                    tk.bots.health[i] -= if cover { dmg * COVERDMG } else { dmg };
+                },
+                None => {
+                    if let Some(b) = tk.bases.iter_mut().find(|b| b.id == t) {
+                        b.health -= dmg;
+                    }
+                },
+            }
+        }
+
+        let bottree = &mut self.bottree;
+        let teambotcount = &mut tk.teambotcount;
+        let teamstats = &mut tk.teamstats;
+        tk.bots.retain(|bt| {
+            if bt.health > Fx::ZERO {
+                return true;
+            }
+            teambotcount[bt.team as usize] -= 1;
+            teamstats[bt.team as usize].lost += 1;
+            bottree.rm_bot(bt.id, bt.x.tile(), bt.y.tile());
+            false
+        });
+
+        // destroyed bases are just gone, and stop spawning
+        tk.bases.retain(|b| b.health > Fx::ZERO);
+    }
+}
@@ -0,0 +1,178 @@
+// fixed point numbers for everything in the simulation
+// every client has to come up with exactly the same ticks, and floats can differ
+// between browsers and platforms (fused ops, trig implementations, casts)
+// so the sim only uses integer math, and floats are just for drawing
+//
+// Fx is 16.16 fixed point in an i32, so positions up to +-32767 with 1/65536 precision
+
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
+
+pub const FRACBITS: u32 = 16;
+
+#[derive(Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash,Default)]
+pub struct Fx(i32);
+
+impl Fx {
+    pub const ZERO: Fx = Fx(0);
+    pub const ONE: Fx = Fx(1 << FRACBITS);
+    pub const MAX: Fx = Fx(i32::MAX);
+    pub const MIN: Fx = Fx(i32::MIN);
+
+    pub const fn from_bits(b: i32) -> Fx {
+        Fx(b)
+    }
+
+    pub const fn to_bits(self) -> i32 {
+        self.0
+    }
+
+    pub const fn from_int(v: i32) -> Fx {
+        Fx(v << FRACBITS)
+    }
+
+    // n / d, rounded to nearest
+    pub const fn from_ratio(n: i32, d: i32) -> Fx {
+        let v = (n as i64) << FRACBITS;
+        let d = d as i64;
+        if (v < 0) == (d < 0) {
+            Fx(((v + (d / 2)) / d) as i32)
+        } else {
+            Fx(((v - (d / 2)) / d) as i32)
+        }
+    }
+
+    // rounds toward negative infinity
+    pub const fn floor(self) -> i32 {
+        self.0 >> FRACBITS
+    }
+
+    // the map tile this is in, negatives end up huge so they fail bounds checks
+    pub const fn tile(self) -> u32 {
+        self.floor() as u32
+    }
+
+    pub fn abs(self) -> Fx {
+        Fx(self.0.abs())
+    }
+
+    pub fn clamp(self, min: Fx, max: Fx) -> Fx {
+        Fx(self.0.clamp(min.0, max.0))
+    }
+
+    // only for drawing, never feed this back into the sim
+    pub fn to_f32(self) -> f32 {
+        (self.0 as f32) / ((1 << FRACBITS) as f32)
+    }
+}
+
+impl fmt::Debug for Fx {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_f32())
+    }
+}
+
+impl Add for Fx {
+    type Output = Fx;
+    fn add(self, o: Fx) -> Fx {
+        Fx(self.0 + o.0)
+    }
+}
+
+impl AddAssign for Fx {
+    fn add_assign(&mut self, o: Fx) {
+        self.0 += o.0;
+    }
+}
+
+impl Sub for Fx {
+    type Output = Fx;
+    fn sub(self, o: Fx) -> Fx {
+        Fx(self.0 - o.0)
+    }
+}
+
+impl SubAssign for Fx {
+    fn sub_assign(&mut self, o: Fx) {
+        self.0 -= o.0;
+    }
+}
+
+impl Neg for Fx {
+    type Output = Fx;
+    fn neg(self) -> Fx {
+        Fx(-self.0)
+    }
+}
+
+// multiplies and divides go through i64 and round toward negative infinity
+impl Mul for Fx {
+    type Output = Fx;
+    fn mul(self, o: Fx) -> Fx {
+        Fx((((self.0 as i64) * (o.0 as i64)) >> FRACBITS) as i32)
+    }
+}
+
+impl MulAssign for Fx {
+    fn mul_assign(&mut self, o: Fx) {
+        *self = *self * o;
+    }
+}
+
+impl Div for Fx {
+    type Output = Fx;
+    fn div(self, o: Fx) -> Fx {
+        if o.0 == 0 {
+            panic!("Fixed point divide by zero");
+        }
+        Fx((((self.0 as i64) << FRACBITS) / (o.0 as i64)) as i32)
+    }
+}
+
+// angles are in 1/ANGLES of a turn, so they can index the trig table
+pub const ANGLES: u32 = 1024;
+
+// sin over a quarter turn, built at compile time with integer math only
+// so every platform gets exactly the same table
+const QUARTER: usize = (ANGLES / 4) as usize;
+static SIN: [i32; QUARTER + 1] = sin_table();
+
+const fn sin_table() -> [i32; QUARTER + 1] {
+    // taylor series at 2^30 precision, good enough that every entry rounds right
+    const ONE: i64 = 1 << 30;
+    const HALFPI: i64 = 1_686_629_713; // pi/2 * 2^30
+
+    let mut t = [0; QUARTER + 1];
+    let mut i = 0;
+    while i <= QUARTER {
+        let x = (HALFPI * (i as i64)) / (QUARTER as i64);
+        let x2 = (x * x) / ONE;
+        let mut term = x;
+        let mut sum = x;
+        let mut k = 1;
+        while k < 12 {
+            term = -(term * x2 / ONE) / ((2 * k) * (2 * k + 1));
+            sum += term;
+            k += 1;
+        }
+        // down to 16 fractional bits, rounded
+        t[i] = ((sum + (1 << 13)) >> 14) as i32;
+        i += 1;
+    }
+    t
+}
+
+pub fn sin(ang: u32) -> Fx {
+    let a = (ang % ANGLES) as usize;
+    let q = a % QUARTER;
+    match a / QUARTER {
+        0 => Fx(SIN[q]),
+        1 => Fx(SIN[QUARTER - q]),
+        2 => Fx(-SIN[q]),
+        _ => Fx(-SIN[QUARTER - q]),
+    }
+}
+
+pub fn cos(ang: u32) -> Fx {
+    sin(ang + (ANGLES / 4))
+}
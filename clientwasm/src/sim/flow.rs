@@ -0,0 +1,200 @@
+// flow fields, so bots can find their way to attract paint from across the map, around walls
+// each team has one, the number of steps from every tile to the closest attract paint on its layer, up to FLOWRAD
+// bots out of steering range of any attract paint go downhill on it
+//
+// working it out over the whole map is a full search, so it is kept from tick to tick
+// new attract paint only spreads out from where it went down, it can only make things closer
+// paint going away (erased, painted over, or faded out) means starting over, on the next tick that needs it
+// either way it comes out the same as starting over, so clients that rolled back or joined late still agree
+
+use std::collections::VecDeque;
+use stratapaint_protocol::PaintDelta;
+
+use super::{GameMap, Px};
+use super::steer::Command;
+
+// no way to get to any attract paint from here, or a tile bots can't be on
+pub const UNREACHED: u32 = u32::MAX;
+
+// how far paint reaches, in steps, so a dot of paint doesn't pull in the whole map
+pub const FLOWRAD: u32 = 64;
+
+pub struct FlowField {
+    pub w: u32,
+    pub h: u32,
+    dist: Vec<u32>, // steps to the closest attract paint, 4 ways
+    strength: Vec<u8>, // the strongest attract paint that close, so fading paint pulls less
+    added: Vec<(u32, u32)>, // attract paint put down since the last update, to spread out from
+    dirty: bool, // something went away, so it all has to be worked out again
+}
+
+fn attract(p: Px) -> Option<u8> {
+    match Command::from_px(p) {
+        Some((Command::Attract, s)) => Some(s),
+        _ => None,
+    }
+}
+
+impl FlowField {
+    // starts out needing a full update
+    pub fn new(w: u32, h: u32) -> FlowField {
+        FlowField {
+            w,
+            h,
+            dist: vec![UNREACHED; (w * h) as usize],
+            strength: vec![0; (w * h) as usize],
+            added: Vec::new(),
+            dirty: true,
+        }
+    }
+
+    pub fn dist(&self, x: u32, y: u32) -> u32 {
+        self.dist[(x + y * self.w) as usize]
+    }
+
+    pub fn strength(&self, x: u32, y: u32) -> u8 {
+        self.strength[(x + y * self.w) as usize]
+    }
+
+    // the layer will have to be looked at again from scratch
+    pub fn invalidate(&mut self) {
+        self.dirty = true;
+        self.added.clear();
+    }
+
+    // paint went down on the layer, old is what it covered up
+    pub fn painted(&mut self, new: &PaintDelta, old: &PaintDelta) {
+        if self.dirty {
+            return;
+        }
+        let mut was = Vec::new();
+        old.for_each(|_, _, c| was.push(c));
+        let mut i = 0;
+        let mut lost = false;
+        new.for_each(|x, y, c| {
+            let before = attract(Px::from(was[i]));
+            let after = attract(Px::from(c));
+            i += 1;
+            match (before, after) {
+                (None, None) => (),
+                (_, Some(s)) if s >= before.unwrap_or(0) => self.added.push((x, y)),
+                _ => lost = true,
+            }
+        });
+        if lost {
+            self.invalidate();
+        }
+    }
+
+    // every pixel on the layer lost amt, and gone is anything that ran out
+    pub fn faded(&mut self, amt: u8, gone: Option<&PaintDelta>) {
+        if self.dirty {
+            return;
+        }
+        let mut lost = false;
+        if let Some(d) = gone {
+            d.for_each(|_, _, c| lost |= attract(Px::from(c)).is_some());
+        }
+        if lost {
+            self.invalidate();
+            return;
+        }
+        // the strongest paint is still the strongest, it is just weaker
+        for (d, s) in self.dist.iter().zip(self.strength.iter_mut()) {
+            if *d != UNREACHED {
+                *s -= amt;
+            }
+        }
+    }
+
+    // catch up with the layer, before anything steers by it
+    pub fn update(&mut self, map: &GameMap, layer: &GameMap) {
+        let mut todo = VecDeque::new();
+        if self.dirty {
+            self.dist.iter_mut().for_each(|d| *d = UNREACHED);
+            self.strength.iter_mut().for_each(|s| *s = 0);
+            for y in 0..self.h {
+                for x in 0..self.w {
+                    self.seed(map, layer, x, y, &mut todo);
+                }
+            }
+            self.dirty = false;
+        } else {
+            for (x, y) in std::mem::take(&mut self.added) {
+                self.seed(map, layer, x, y, &mut todo);
+            }
+        }
+        self.spread(map, todo);
+    }
+
+    fn seed(&mut self, map: &GameMap, layer: &GameMap, x: u32, y: u32, todo: &mut VecDeque<(u32, u32)>) {
+        // paint on a wall still counts, but there's no getting onto it
+        if map.get_tile(x, y).blocks_move() {
+            return;
+        }
+        if let Some(s) = attract(layer.get(x, y)) {
+            let i = (x + y * self.w) as usize;
+            if self.dist[i] != 0 || self.strength[i] < s {
+                self.dist[i] = 0;
+                self.strength[i] = s;
+                todo.push_back((x, y));
+            }
+        }
+    }
+
+    // breadth first out from whatever changed, only going where it makes things closer
+    // or as close but to stronger paint, so it ends up the same whatever order paint went down in
+    fn spread(&mut self, map: &GameMap, mut todo: VecDeque<(u32, u32)>) {
+        while let Some((x, y)) = todo.pop_front() {
+            let i = (x + y * self.w) as usize;
+            let (d, s) = (self.dist[i] + 1, self.strength[i]);
+            if d > FLOWRAD {
+                continue;
+            }
+            let next = [
+                (x > 0).then(|| (x - 1, y)),
+                (x + 1 < self.w).then(|| (x + 1, y)),
+                (y > 0).then(|| (x, y - 1)),
+                (y + 1 < self.h).then(|| (x, y + 1)),
+            ];
+            for (nx, ny) in next.iter().flatten() {
+                let j = (nx + ny * self.w) as usize;
+                if d > self.dist[j] || (d == self.dist[j] && s <= self.strength[j]) {
+                    continue;
+                }
+                if map.get_tile(*nx, *ny).blocks_move() {
+                    continue;
+                }
+                self.dist[j] = d;
+                self.strength[j] = s;
+                todo.push_back((*nx, *ny));
+            }
+        }
+    }
+
+    // which way is downhill from a tile, in -2..=2 on each axis, and how strong the paint is at the bottom
+    // None on the paint itself, or with nowhere to go
+    pub fn downhill(&self, x: i32, y: i32) -> Option<(i64, i64, u8)> {
+        if x < 0 || y < 0 || x >= self.w as i32 || y >= self.h as i32 {
+            return None;
+        }
+        let d0 = self.dist(x as u32, y as u32);
+        if d0 == 0 || d0 == UNREACHED {
+            return None;
+        }
+
+        // walls and the edge are as good as going the wrong way
+        let d = |x: i32, y: i32| -> i64 {
+            if x < 0 || y < 0 || x >= self.w as i32 || y >= self.h as i32 {
+                return d0 as i64 + 1;
+            }
+            (self.dist(x as u32, y as u32) as i64).min(d0 as i64 + 1)
+        };
+        let gx = d(x - 1, y) - d(x + 1, y);
+        let gy = d(x, y - 1) - d(x, y + 1);
+        if gx == 0 && gy == 0 {
+            return None;
+        }
+        Some((gx, gy, self.strength(x as u32, y as u32)))
+    }
+}
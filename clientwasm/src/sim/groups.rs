@@ -0,0 +1,129 @@
+// bots bucketed by location, so we only have to look at nearby bots
+// each bucket is kept sorted by id, so going through one is the same on every client
+// no matter what order bots moved in (or if the groups were rebuilt from a snapshot)
+//
+// the groups only know which tile a bot is on, so queries take the bots to check exact positions
+// distances use the same P-Inf norm as everything else in the sim, so a range r is a square
+// 2r across, not a circle, and bots out on the diagonal up to r * sqrt(2) away still count
+
+use super::{Bots, BotState, Fx};
+
+#[derive(Clone)]
+pub struct LocationGroups {
+    pub groupw: u32,
+    pub grouph: u32,
+    pub vecs: Vec<Vec<u32>>,
+}
+
+// LocationGroup constants
+pub const GROUPSHIFT: u32 = 3; // 8x8 tiles, small enough that combat range only covers a few groups
+
+impl LocationGroups {
+    pub fn new(mapw: u32, maph: u32) -> LocationGroups {
+        let groupw = (mapw >> GROUPSHIFT)+1;
+        let grouph = (maph >> GROUPSHIFT)+1;
+        let mut vecs: Vec<Vec<u32>> = Vec::new();
+        for _ in 0..(groupw * grouph) {
+            vecs.push(Vec::new());
+        }
+        LocationGroups {
+            groupw,
+            grouph,
+            vecs,
+        }
+    }
+
+    pub fn add_bot(&mut self, id: u32, x: u32, y: u32) {
+        let xgroup = x >> GROUPSHIFT;
+        let ygroup = y >> GROUPSHIFT;
+
+        let v = &mut self.vecs[(xgroup + (ygroup * self.groupw)) as usize];
+        match v.binary_search(&id) {
+            Ok(_) => panic!("Tried to add a bot to LocationGroups twice!"),
+            Err(i) => v.insert(i, id),
+        }
+    }
+
+    pub fn rm_bot(&mut self, id: u32, x: u32, y: u32) {
+        let xgroup = x >> GROUPSHIFT;
+        let ygroup = y >> GROUPSHIFT;
+
+        let v = &mut self.vecs[(xgroup + (ygroup * self.groupw)) as usize];
+
+        match v.binary_search(&id) {
+            Ok(i) => {
+                v.remove(i);
+            },
+            Err(_) => panic!("Didn't find bot where it was supposed to be in LocationGroups!"),
+        }
+    }
+
+    pub fn move_bot(&mut self, id: u32, old_x: u32, old_y: u32, new_x: u32, new_y: u32) {
+        if ((old_x >> GROUPSHIFT) != (new_x >> GROUPSHIFT)) || ((old_y >> GROUPSHIFT) != (new_y >> GROUPSHIFT)) {
+            self.rm_bot(id, old_x, old_y);
+            self.add_bot(id, new_x, new_y);
+        }
+    }
+
+    // groups from lo to hi along one side, None if it is all off the map
+    fn span(lo: Fx, hi: Fx, ngroups: u32) -> Option<(u32, u32)> {
+        if hi < Fx::ZERO || lo > hi {
+            return None;
+        }
+        let lo = lo.max(Fx::ZERO).tile() >> GROUPSHIFT;
+        if lo >= ngroups {
+            return None;
+        }
+        let hi = (hi.tile() >> GROUPSHIFT).min(ngroups - 1);
+        Some((lo, hi))
+    }
+
+    // bots in the groups covering a box, in no particular order
+    fn candidates(&self, xmin: Fx, ymin: Fx, xmax: Fx, ymax: Fx) -> Vec<u32> {
+        let mut ids = Vec::new();
+        let (xs, ys) = match (LocationGroups::span(xmin, xmax, self.groupw), LocationGroups::span(ymin, ymax, self.grouph)) {
+            (Some(xs), Some(ys)) => (xs, ys),
+            _ => return ids,
+        };
+        for yg in ys.0..=ys.1 {
+            for xg in xs.0..=xs.1 {
+                ids.extend_from_slice(&self.vecs[(xg + (yg * self.groupw)) as usize]);
+            }
+        }
+        ids
+    }
+
+    // bots with xmin <= x <= xmax and ymin <= y <= ymax, in id order
+    pub fn in_rect(&self, bots: &Bots, xmin: Fx, ymin: Fx, xmax: Fx, ymax: Fx) -> Vec<u32> {
+        let mut ids = self.candidates(xmin, ymin, xmax, ymax);
+        ids.retain(|id| {
+            let i = bots.index(*id).expect("LocationGroups has a bot that isn't in the tick");
+            bots.x[i] >= xmin && bots.x[i] <= xmax && bots.y[i] >= ymin && bots.y[i] <= ymax
+        });
+        ids.sort_unstable();
+        ids
+    }
+
+    // bots in the square reaching r out from (x, y) each way, in id order
+    pub fn in_square(&self, bots: &Bots, x: Fx, y: Fx, r: Fx) -> Vec<u32> {
+        self.in_rect(bots, x - r, y - r, x + r, y + r)
+    }
+
+    // up to k of the closest bots in the square reaching r out that pass the filter (a team check, or leaving one out)
+    // closest first by the P-Inf norm, ties go to the lower id
+    pub fn nearest<F>(&self, bots: &Bots, x: Fx, y: Fx, r: Fx, k: usize, filter: F) -> Vec<u32>
+        where F: Fn(&BotState) -> bool
+    {
+        let mut found: Vec<(Fx, u32)> = Vec::new();
+        for id in self.candidates(x - r, y - r, x + r, y + r) {
+            let b = bots.get(id).expect("LocationGroups has a bot that isn't in the tick");
+            let d = (b.x - x).abs().max((b.y - y).abs());
+            if d <= r && filter(&b) {
+                found.push((d, id));
+            }
+        }
+        found.sort_unstable();
+        found.truncate(k);
+        found.into_iter().map(|(_, id)| id).collect()
+    }
+}
@@ -0,0 +1,113 @@
+// a hash of everything in a tick, sent back with acks so the server can spot desyncs
+// has to come out the same on every platform, so no std Hasher (it can change between
+// releases, and hashes integers in native byte order)
+
+use super::{GameTick, GameMap, Fx};
+
+// fnv-1a 64
+pub struct StateHash(u64);
+
+impl StateHash {
+    pub fn new() -> StateHash {
+        StateHash(0xcbf29ce484222325)
+    }
+
+    pub fn bytes(&mut self, b: &[u8]) {
+        for v in b {
+            self.0 ^= *v as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    pub fn u32(&mut self, v: u32) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    // a whole word in one step, for bulk data where a byte at a time is too slow
+    // every step is still reversible, so any one word being different still changes the hash
+    pub fn u64(&mut self, v: u64) {
+        self.0 ^= v;
+        self.0 = self.0.wrapping_mul(0x100000001b3);
+    }
+
+    pub fn fx(&mut self, v: Fx) {
+        self.u32(v.to_bits() as u32);
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for StateHash {
+    fn default() -> Self {
+        StateHash::new()
+    }
+}
+
+impl GameTick {
+    // only valid on the current tick, older ticks don't keep their paint layers
+    pub fn hash(&self) -> u64 {
+        self.hash_with(&self.paints)
+    }
+
+    // for older ticks, with their paint layers from Game::paints_at
+    pub fn hash_with(&self, paints: &[GameMap]) -> u64 {
+        let mut h = StateHash::new();
+        h.u32(self.tick);
+
+        h.u32(self.bases.len() as u32);
+        for b in self.bases.iter() {
+            h.u32(b.id);
+            h.fx(b.health);
+            h.fx(b.nextspawn);
+            h.fx(b.x);
+            h.fx(b.y);
+            h.u32(b.team as u32);
+        }
+
+        // bots are kept in id order
+        h.u32(self.bots.len() as u32);
+        for b in self.bots.iter() {
+            h.u32(b.id);
+            h.fx(b.health);
+            h.fx(b.x);
+            h.fx(b.y);
+            h.fx(b.vx);
+            h.fx(b.vy);
+            h.u32(b.team as u32);
+            h.u32(b.cur_target);
+        }
+
+        h.u32(self.teambotcount.len() as u32);
+        for (i, (c, st)) in self.teambotcount.iter().zip(self.teamstats.iter()).enumerate() {
+            h.u32(*c);
+            h.u32(st.spawned);
+            h.u32(st.lost);
+            h.u32(self.ink[i]);
+        }
+
+        h.u32(paints.len() as u32);
+        for p in paints.iter() {
+            h.u32(p.w);
+            h.u32(p.h);
+            // the layers are most of the state on a big map, so two pixels at a time
+            for px in p.data.chunks(2) {
+                let lo = u32::from(px[0]) as u64;
+                let hi = px.get(1).map_or(0, |p| u32::from(*p) as u64);
+                h.u64(lo | (hi << 32));
+            }
+        }
+
+        // the rest of the result comes from what is already hashed
+        match &self.over {
+            Some(r) => {
+                h.u32(r.tick);
+                h.u32(r.winner.map_or(0, |w| w + 1));
+            },
+            None => h.u32(0),
+        }
+
+        h.finish()
+    }
+}
@@ -0,0 +1,55 @@
+// paint costs ink, so nobody can paint the whole map every tick
+// each team has its own, and gets some back every game second, more for each base it still has
+// ink is counted in 1/255ths of a full strength pixel, so weaker paint costs less and it is all whole numbers
+// paint a team can't pay for is dropped when the netstep is run, the same way on every client
+
+use stratapaint_protocol::PaintDelta;
+
+use super::{Game, GameTick, Px, Fx};
+use super::steer::Command;
+
+// ink constants, in full strength pixels
+const PIXEL: u32 = 0xff;
+pub const MAXINK: u32 = 40000 * PIXEL;
+pub const STARTINK: u32 = 20000 * PIXEL;
+const INKREGEN: i64 = 200; // per game second
+const BASEINK: i64 = 300; // more per game second for each base
+
+// what a pixel of each command costs at full strength
+// erasing is free, it only takes paint away
+fn pixel_cost(c: Command) -> u64 {
+    match c {
+        Command::Attract | Command::Repel | Command::Dir(_) => 1,
+        Command::Hold => 2,
+    }
+}
+
+// the ink it takes to paint a delta
+// d must have passed paint::check, so it is all commands or clearing
+pub fn paint_cost(d: &PaintDelta) -> u64 {
+    let mut total = 0;
+    d.for_each(|_, _, color| {
+        if let Some((cmd, strength)) = Command::from_px(Px::from(color)) {
+            total += pixel_cost(cmd) * (strength as u64);
+        }
+    });
+    total
+}
+
+impl Game {
+    // how much ink each team gets back this tick
+    pub(super) fn regen_ink(&self, tk: &mut GameTick) {
+        for team in 0..tk.ink.len() {
+            let bases = tk.bases.iter().filter(|b| b.team as usize == team).count() as i64;
+            let persec = (INKREGEN + BASEINK * bases) * (PIXEL as i64);
+            let add = (persec * (self.tickstep.to_bits() as i64)) >> super::fixed::FRACBITS;
+            tk.ink[team] = (tk.ink[team] as i64 + add).min(MAXINK as i64) as u32;
+        }
+    }
+
+    // ink as a part of the most a team can have, for showing players
+    pub fn ink_left(&self, team: usize) -> Fx {
+        let ink = self.get_cur_tick().ink.get(team).copied().unwrap_or(0);
+        Fx::from_bits((((ink as i64) << super::fixed::FRACBITS) / (MAXINK as i64)) as i32)
+    }
+}
@@ -0,0 +1,234 @@
+use super::{fixed, Fx};
+
+#[derive(PartialEq,Eq)]
+#[derive(Clone,Copy)]
+#[repr(C, packed)]
+pub struct Px {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Px {
+    pub const WHITE: Px = Px{
+        r: 0xff,
+        g: 0xff,
+        b: 0xff,
+        a: 0xff,
+    };
+    pub const BLACK: Px = Px{
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 0xff,
+    };
+    pub const GREY: Px = Px{
+        r: 0x80,
+        g: 0x80,
+        b: 0x80,
+        a: 0xff,
+    };
+    pub const BROWN: Px = Px{
+        r: 0x8b,
+        g: 0x5a,
+        b: 0x2b,
+        a: 0xff,
+    };
+    pub const BLUE: Px = Px{
+        r: 0x2a,
+        g: 0x6f,
+        b: 0xdb,
+        a: 0xff,
+    };
+    pub const CLEAR: Px = Px{
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 0,
+    };
+}
+
+// colors go over the network as 0xRRGGBBAA
+impl From<u32> for Px {
+    fn from(c: u32) -> Self {
+        let b = c.to_be_bytes();
+        Px{
+            r: b[0],
+            g: b[1],
+            b: b[2],
+            a: b[3],
+        }
+    }
+}
+
+impl From<Px> for u32 {
+    fn from(p: Px) -> Self {
+        u32::from_be_bytes([p.r, p.g, p.b, p.a])
+    }
+}
+
+#[derive(Clone)]
+pub struct GameMap {
+    pub w: u32,
+    pub h: u32,
+    pub data: Box<[Px]>, // can be color, or for base map it is terrain info
+}
+
+impl GameMap {
+    pub fn new(w: u32, h: u32) -> GameMap {
+        GameMap{
+            w,
+            h,
+            data: (vec![Px::CLEAR; (w * h) as usize]).into_boxed_slice(),
+        }
+    }
+
+    pub fn set_tile(&mut self, x: u32, y: u32, tile: MapTiles) {
+        let p: Px = tile.into();
+        self.set(x, y, p);
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, color: Px) {
+        if (x >= self.w) || (y >= self.h) {
+            panic!("Tried to access map past bounds");
+        }
+        let ind = (x + (y * self.w)) as usize;
+        self.data[ind] = color;
+    }
+
+    pub fn get_tile(&self, x: u32, y: u32) -> MapTiles {
+        self.get(x, y).into()
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Px {
+        if (x >= self.w) || (y >= self.h) {
+            panic!("Tried to access map past bounds");
+        }
+        let ind = (x + (y * self.w)) as usize;
+        self.data[ind]
+    }
+
+    // if nothing on the map is in the way between two spots, both on the map
+    pub fn clear_sight(&self, x0: Fx, y0: Fx, x1: Fx, y1: Fx) -> bool {
+        walk_line(x0, y0, x1, y1, |x, y, _| !self.get_tile(x, y).blocks_sight())
+    }
+
+    // where moving between two spots on the map first runs into something bots can't go through, if it does
+    // the tile it starts in doesn't count, so anything stuck in a wall can still get out, but not into another one
+    pub fn sweep(&self, x0: Fx, y0: Fx, x1: Fx, y1: Fx) -> Option<Hit> {
+        let mut hit = None;
+        walk_line(x0, y0, x1, y1, |x, y, crossed| {
+            if crossed != Hit::default() && self.get_tile(x, y).blocks_move() {
+                hit = Some(crossed);
+                return false;
+            }
+            true
+        });
+        hit
+    }
+}
+
+// which tile edges got crossed to get into a tile, so which way to bounce off of it
+// neither for the tile a line starts in
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Default)]
+pub struct Hit {
+    pub x: bool,
+    pub y: bool,
+}
+
+// go through every tile a line touches, in order, starting with the one it starts in
+// stops early if f returns false, and returns if we made it to the end
+// a line going exactly through a corner touches both tiles beside the corner too, before the one across it
+// all integer math, on the raw fixed point bits
+pub fn walk_line<F: FnMut(u32, u32, Hit) -> bool>(x0: Fx, y0: Fx, x1: Fx, y1: Fx, mut f: F) -> bool {
+    const ONE: i64 = 1 << fixed::FRACBITS;
+    let (ax, ay) = (x0.to_bits() as i64, y0.to_bits() as i64);
+    let (dx, dy) = (x1.to_bits() as i64 - ax, y1.to_bits() as i64 - ay);
+    let (mut tx, mut ty) = (x0.tile(), y0.tile());
+    let (endx, endy) = (x1.tile(), y1.tile());
+
+    // how far along each axis to the next tile edge
+    let mut ex = if dx > 0 { (tx as i64 + 1) * ONE - ax } else { ax - (tx as i64) * ONE };
+    let mut ey = if dy > 0 { (ty as i64 + 1) * ONE - ay } else { ay - (ty as i64) * ONE };
+    let stepx = |t: u32| if dx > 0 { t + 1 } else { t - 1 };
+    let stepy = |t: u32| if dy > 0 { t + 1 } else { t - 1 };
+
+    let mut crossed = Hit::default();
+    loop {
+        if !f(tx, ty, crossed) {
+            return false;
+        }
+        if tx == endx && ty == endy {
+            return true;
+        }
+
+        // whichever edge the line gets to first, ex/|dx| against ey/|dy|
+        let cx = if tx == endx { i64::MAX } else { ex.saturating_mul(dy.abs()) };
+        let cy = if ty == endy { i64::MAX } else { ey.saturating_mul(dx.abs()) };
+        if cx == cy {
+            if !f(stepx(tx), ty, Hit{x: true, y: false}) || !f(tx, stepy(ty), Hit{x: false, y: true}) {
+                return false;
+            }
+            tx = stepx(tx);
+            ty = stepy(ty);
+            ex += ONE;
+            ey += ONE;
+            crossed = Hit{x: true, y: true};
+        } else if cx < cy {
+            tx = stepx(tx);
+            ex += ONE;
+            crossed = Hit{x: true, y: false};
+        } else {
+            ty = stepy(ty);
+            ey += ONE;
+            crossed = Hit{x: false, y: true};
+        }
+    }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum MapTiles {
+    Wall,
+    Nothing,
+    Unk,
+    Cover, // bots on it take less damage
+    Mud, // bots on it can't go as fast
+    Water, // or a chasm, can't go across it but can shoot across it
+}
+
+impl MapTiles {
+    pub fn blocks_move(self) -> bool {
+        self == MapTiles::Wall || self == MapTiles::Water
+    }
+
+    pub fn blocks_sight(self) -> bool {
+        self == MapTiles::Wall
+    }
+}
+
+impl From<Px> for MapTiles {
+    fn from(orig: Px) -> Self {
+        match orig {
+            Px::WHITE => MapTiles::Nothing,
+            Px::BLACK => MapTiles::Wall,
+            Px::GREY => MapTiles::Cover,
+            Px::BROWN => MapTiles::Mud,
+            Px::BLUE => MapTiles::Water,
+            _ => MapTiles::Unk,
+        }
+    }
+}
+
+impl From<MapTiles> for Px {
+    fn from(orig: MapTiles) -> Self {
+        match orig {
+            MapTiles::Nothing => Px::WHITE,
+            MapTiles::Wall => Px::BLACK,
+            MapTiles::Unk => Px::CLEAR,
+            MapTiles::Cover => Px::GREY,
+            MapTiles::Mud => Px::BROWN,
+            MapTiles::Water => Px::BLUE,
+        }
+    }
+}
@@ -0,0 +1,144 @@
+// loading maps from images, instead of making one up from the seed
+// the server ships the png to every client in the game info, and everyone loads it the same way
+//
+// map colors (0xRRGGBBAA)
+//  open ground  ff ff ff ff  (or anything with zero alpha)
+//  wall         00 00 00 ff
+//  cover        80 80 80 ff
+//  mud          8b 5a 2b ff
+//  water        2a 6f db ff
+//  base         ff 00 tt ff  a base for team tt is centered on this pixel
+//  spawn point  00 ff tt ff  team tt's starting bots are spread around these, or around its bases if it has none
+// bases and spawn points are open ground under them
+// markers for teams past the number in the game are just open ground, so one map works for fewer teams
+
+use std::fmt;
+
+use super::{GameMap, MapTiles, Px, Fx, MAXMAPDIM};
+
+const PNGSIG: &[u8] = &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+#[derive(Debug,PartialEq,Eq)]
+pub enum MapError {
+    Png(String),
+    Size(u32, u32),
+    BadColor(u32, u32, u32), // x, y, color
+    NoBase(u32),
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapError::Png(e) => write!(f, "couldn't read png: {}", e),
+            MapError::Size(w, h) => write!(f, "map is {}x{}, has to be between 1x1 and {}x{}", w, h, MAXMAPDIM, MAXMAPDIM),
+            MapError::BadColor(x, y, c) => write!(f, "map color {:08x} at {},{} isn't in the palette", c, x, y),
+            MapError::NoBase(t) => write!(f, "map has no base for team {}", t),
+        }
+    }
+}
+
+// a spot on the map for a team, in the middle of its pixel
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct Marker {
+    pub team: u32,
+    pub x: Fx,
+    pub y: Fx,
+}
+
+// a loaded map, the terrain and where everything starts out
+#[derive(Clone)]
+pub struct MapFile {
+    pub map: GameMap,
+    pub bases: Vec<Marker>, // in the order they are in the image, row by row
+    pub spawns: Vec<Marker>,
+}
+
+impl MapFile {
+    // 4 bytes per pixel, row major, like a canvas ImageData
+    pub fn from_rgba(w: u32, h: u32, data: &[u8]) -> Result<MapFile, MapError> {
+        if w == 0 || h == 0 || w > MAXMAPDIM || h > MAXMAPDIM {
+            return Err(MapError::Size(w, h));
+        }
+        if data.len() != (w as usize) * (h as usize) * 4 {
+            return Err(MapError::Size(w, h));
+        }
+
+        let mut mf = MapFile {
+            map: GameMap::new(w, h),
+            bases: Vec::new(),
+            spawns: Vec::new(),
+        };
+        for (i, px) in data.chunks_exact(4).enumerate() {
+            let x = (i as u32) % w;
+            let y = (i as u32) / w;
+            let marker = |team: u8| Marker {
+                team: team as u32,
+                x: Fx::from_int(x as i32) + Fx::from_ratio(1, 2),
+                y: Fx::from_int(y as i32) + Fx::from_ratio(1, 2),
+            };
+
+            let tile = match (px[0], px[1], px[2], px[3]) {
+                (_, _, _, 0) => MapTiles::Nothing,
+                (0xff, 0, t, 0xff) => {
+                    mf.bases.push(marker(t));
+                    MapTiles::Nothing
+                },
+                (0, 0xff, t, 0xff) => {
+                    mf.spawns.push(marker(t));
+                    MapTiles::Nothing
+                },
+                (r, g, b, a) => match MapTiles::from(Px{r, g, b, a}) {
+                    MapTiles::Unk => return Err(MapError::BadColor(x, y, u32::from(Px{r, g, b, a}))),
+                    t => t,
+                },
+            };
+            mf.map.set_tile(x, y, tile);
+        }
+        Ok(mf)
+    }
+
+    // a png file, or w x h of raw rgba pixels
+    // can't mix them up, a valid rgba map never starts with the png signature, that first pixel isn't in the palette
+    pub fn load(w: u32, h: u32, data: &[u8]) -> Result<MapFile, MapError> {
+        if data.starts_with(PNGSIG) {
+            MapFile::from_png(data)
+        } else {
+            MapFile::from_rgba(w, h, data)
+        }
+    }
+
+    pub fn from_png(data: &[u8]) -> Result<MapFile, MapError> {
+        let png_err = |e: png::DecodingError| MapError::Png(e.to_string());
+
+        // palettes and low bit depths get expanded out, 16 bit gets cut down to 8
+        let mut decoder = png::Decoder::new(data);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info().map_err(png_err)?;
+        let (w, h) = (reader.info().width, reader.info().height);
+        if w == 0 || h == 0 || w > MAXMAPDIM || h > MAXMAPDIM {
+            return Err(MapError::Size(w, h));
+        }
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut buf).map_err(png_err)?;
+        let buf = &buf[..frame.buffer_size()];
+
+        let rgba: Vec<u8> = match frame.color_type {
+            png::ColorType::Rgba => buf.to_vec(),
+            png::ColorType::Rgb => buf.chunks_exact(3).flat_map(|p| vec![p[0], p[1], p[2], 0xff]).collect(),
+            png::ColorType::GrayscaleAlpha => buf.chunks_exact(2).flat_map(|p| vec![p[0], p[0], p[0], p[1]]).collect(),
+            png::ColorType::Grayscale => buf.iter().flat_map(|v| vec![*v, *v, *v, 0xff]).collect(),
+            png::ColorType::Indexed => return Err(MapError::Png(String::from("palette wasn't expanded"))),
+        };
+        MapFile::from_rgba(w, h, &rgba)
+    }
+
+    // every team in the game needs somewhere to start
+    pub fn check_teams(&self, nteams: u32) -> Result<(), MapError> {
+        for t in 0..nteams {
+            if !self.bases.iter().any(|b| b.team == t) {
+                return Err(MapError::NoBase(t));
+            }
+        }
+        Ok(())
+    }
+}
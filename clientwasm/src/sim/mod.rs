@@ -0,0 +1,795 @@
+use rand_xorshift::XorShiftRng;
+use rand::SeedableRng;
+use rand::Rng;
+use stratapaint_protocol::{GameResult, NetStep, PaintDelta, PlayerId, TeamResult};
+
+use crate::log;
+
+pub mod fixed;
+mod map;
+mod groups;
+mod bots;
+mod paint;
+mod snapshot;
+mod hash;
+pub mod steer;
+mod combat;
+mod mapfile;
+mod ink;
+mod flow;
+
+pub use map::{Px, GameMap, MapTiles, Hit};
+pub use groups::{LocationGroups, GROUPSHIFT};
+pub use bots::Bots;
+pub use paint::{PaintError, MAXPAINTAREA};
+pub use fixed::Fx;
+pub use hash::StateHash;
+pub use combat::BOTRANGE;
+pub use mapfile::{MapFile, MapError, Marker};
+pub use ink::{paint_cost, MAXINK, STARTINK};
+pub use flow::{FlowField, UNREACHED, FLOWRAD};
+
+// bot state
+#[derive(Clone,Copy)]
+pub struct BotState {
+    pub id: u32,
+    pub health: Fx,
+    pub x: Fx, // these are in units of position on GameMap, not canvas. But we treat the canvas as px == GameMap unit, and just transform the canvas
+    pub y: Fx,
+    pub vx: Fx,
+    pub vy: Fx,
+    pub team: i32,
+    pub cur_target: u32, // id of the bot we are shooting at, 0 for none
+}
+
+// bot constants
+pub const BOTRAD: f64 = 0.69; // only for drawing
+const BOTHEALTH: Fx = Fx::from_int(100);
+const BOTRANDMAXACC: Fx = Fx::from_int(6);
+const BOTMAXVEL: Fx = Fx::from_int(9);
+const MUDMAXVEL: Fx = Fx::from_int(3); // when in mud
+const BOTBOUNCEAMT: Fx = Fx::from_ratio(42, 100);
+const BOTCOLSZ: Fx = Fx::from_ratio(1035, 10000); // BOTRAD * 0.15
+
+// base constants
+pub const BASERAD: u32 = 6; // in map tiles, kept clear of walls
+const BASEHEALTH: Fx = Fx::from_int(1000);
+const BASESPAWNTIME: Fx = Fx::from_ratio(1, 2); // game seconds between spawns
+const MAXTEAMBOTS: u32 = 1500; // bases stop spawning past this
+
+// base state
+#[derive(Clone,Copy)]
+pub struct BaseState {
+    pub id: u32,
+    pub health: Fx,
+    pub nextspawn: Fx, // in game time seconds (based on ticks and tick_rate)
+    pub x: Fx,
+    pub y: Fx,
+    pub team: i32,
+}
+
+// running totals for a team, for the end of game results
+#[derive(Clone,Copy,Default,Debug,PartialEq,Eq)]
+pub struct TeamStats {
+    pub spawned: u32, // from bases, not counting the starting bots
+    pub lost: u32,
+}
+
+// game tick structure
+// has to contain the current paint layers, and the current bot states, the base states
+// anything needed for the display needs to be here, because it can lag
+// and anything that can come from the "future" from the network: other player's paints
+//
+// full paint layers are only kept on the current tick, they get moved along to each new tick
+// older ticks just keep what their paint covered up, so going back costs what changed, not the map size
+#[derive(Clone)]
+pub struct GameTick {
+    pub tick: u32,
+    pub bases: Vec<BaseState>,
+    pub bots: Bots,
+    pub teambotcount: Vec<u32>,
+    pub teamstats: Vec<TeamStats>,
+    pub ink: Vec<u32>, // per team, see ink.rs
+    pub paints: Vec<GameMap>, // empty on every tick but the current one
+    pub unpaint: Vec<(usize, PaintDelta)>, // team and what was there before, for each update applied this tick
+    pub unfade: Vec<(usize, PaintDelta)>, // team and the paint that faded all the way away this tick
+    pub over: Option<GameResult>, // set from the tick the game ended on
+    pub nextid: u32, // objidcntr as of this tick, to put back on a rollback
+}
+
+// game structure
+// has to contain a vec of GameTicks we have processed or are working on
+// also contains static game info, including the board layout
+// nothing in here knows about the browser, drawing is done on top of this in lib.rs
+pub struct Game {
+    pub bottree: LocationGroups, // collection of bots in curtick, used for avoiding and targeting nearby bots
+    pub flows: Vec<FlowField>, // per team, for finding the way to the attract paint in curtick
+    pub states: Vec<GameTick>, // vector must always have the newest ticks (higher number) at lower indexes
+    pub tickratio: u32, // # of ticks before a netstep tick, doesn't change
+    pub tickstep: Fx, // game seconds per tick, doesn't change (try to keep real seconds per tick similar to this)
+    pub curtick: u32, // next tick to process
+    pub map: GameMap, // the static map (walls and cover) below the changing paint layers
+    pub baseseed: u32,
+    pub objidcntr: u32,
+    pub maxpredict: u32, // how many netsteps we can run ahead of the server on a guess, 0 to always wait
+    pub decay: u32, // paint strength (out of 0xff) lost per game second, 0 for paint that stays forever
+    netsteps: Vec<NetStep>, // netsteps we have recieved, in order, kept until we can't roll back to them
+    predicted: Vec<u32>, // netsteps we started without their input, in order
+}
+
+pub enum TickResult {
+    Waiting, // don't have the netstep for the next tick yet
+    Ticked,
+    NetStep(u32), // ticked, and started the given netstep
+    Predicted(u32), // ticked, and started the given netstep guessing there was no paint in it
+}
+
+// Game constants
+const STARTID: u32 = 1;
+pub const MAXMAPDIM: u32 = 4096;
+pub const DECAY: u32 = 4; // paint strength lost per game second, so full strength paint lasts about a minute
+pub const MAXTEAMS: u32 = 8;
+const STARTBOTS: u32 = 750; // per team
+const MAPSPREAD: Fx = Fx::from_int(12); // how far starting bots go from their spawn points on a loaded map
+
+impl Game {
+    // tickstep is in game seconds per tick
+    pub fn new(mapw: u32, maph: u32, nteams: u32, tickratio: u32, tickstep: Fx, seed: u32) -> Game {
+        let mut game = Game::empty(mapw, maph, nteams, tickratio, tickstep, seed);
+        game.init_state(nteams);
+        game
+    }
+
+    // a game with an open map and no bots or bases, for setting things up by hand
+    pub fn empty(mapw: u32, maph: u32, nteams: u32, tickratio: u32, tickstep: Fx, seed: u32) -> Game {
+        if tickratio == 0 {
+            panic!("Tried to make a game with zero ticks per netstep");
+        }
+        if mapw > MAXMAPDIM || maph > MAXMAPDIM {
+            panic!("Tried to make a game with too big of a map");
+        }
+        if nteams == 0 || nteams > MAXTEAMS {
+            panic!("Tried to make a game with a bad number of teams");
+        }
+
+        let mut game = Game {
+            states: Vec::new(),
+            bottree: LocationGroups::new(mapw, maph),
+            flows: (0..nteams).map(|_| FlowField::new(mapw, maph)).collect(),
+            tickratio,
+            tickstep,
+            curtick: 0,
+            map: GameMap::new(mapw, maph),
+            baseseed: seed,
+            objidcntr: STARTID,
+            maxpredict: 0,
+            decay: DECAY,
+            netsteps: Vec::new(),
+            predicted: Vec::new(),
+        };
+
+        // per team layers
+        let mut tk = GameTick {
+            tick: 0,
+            bases: Vec::new(),
+            bots: Bots::new(),
+            teambotcount: Vec::new(),
+            teamstats: Vec::new(),
+            ink: Vec::new(),
+            paints: Vec::new(),
+            unpaint: Vec::new(),
+            unfade: Vec::new(),
+            over: None,
+            nextid: STARTID,
+        };
+        for _ in 0..nteams {
+            tk.teambotcount.push(0);
+            tk.teamstats.push(TeamStats::default());
+            tk.ink.push(STARTINK);
+            tk.paints.push(GameMap::new(mapw, maph));
+        }
+        game.states.push(tk);
+
+        game
+    }
+
+    fn add_bot(&mut self, tk: &mut GameTick, x: Fx, y: Fx, id: u32, team: i32) {
+        tk.bots.insert(BotState {
+            id,
+            health: BOTHEALTH,
+            x,
+            y,
+            vx: Fx::ZERO,
+            vy: Fx::ZERO,
+            cur_target: 0,
+            team,
+        });
+        tk.teambotcount[team as usize] += 1;
+        tk.nextid = tk.nextid.max(id + 1);
+
+        // add to tree
+        self.bottree.add_bot(id, x.tile(), y.tile());
+    }
+
+    // the first spawn is BASESPAWNTIME after the start of the game, not after the base went in
+    fn add_base(tk: &mut GameTick, x: Fx, y: Fx, id: u32, team: i32) {
+        tk.bases.push(BaseState {
+            id,
+            health: BASEHEALTH,
+            nextspawn: BASESPAWNTIME,
+            x,
+            y,
+            team,
+        });
+        tk.nextid = tk.nextid.max(id + 1);
+    }
+
+    // where a team starts out, spread evenly around the middle of the map
+    // one team just gets the middle
+    pub fn team_start(&self, team: u32, nteams: u32) -> (Fx, Fx) {
+        let midx = Fx::from_ratio(self.map.w as i32, 2);
+        let midy = Fx::from_ratio(self.map.h as i32, 2);
+        if nteams == 1 {
+            return (midx, midy);
+        }
+
+        let rad = Fx::from_ratio(self.map.w.min(self.map.h) as i32, 3);
+        let ang = team * (fixed::ANGLES / nteams);
+        (midx + (fixed::cos(ang) * rad), midy + (fixed::sin(ang) * rad))
+    }
+
+    // put a bot in the current tick, for setting up games by hand
+    pub fn spawn_bot(&mut self, x: Fx, y: Fx, team: i32) -> u32 {
+        let i = self.states.iter().position(|t| t.tick == self.curtick).expect("Could not find curtick in states vector");
+        if team < 0 || team as usize >= self.states[i].teambotcount.len() {
+            panic!("Tried to spawn a bot on a team that doesn't exist");
+        }
+        if x.tile() >= self.map.w || y.tile() >= self.map.h {
+            panic!("Tried to spawn a bot off the map");
+        }
+
+        let mut tk = self.states.remove(i);
+        let id = self.objidcntr;
+        self.objidcntr += 1;
+        self.add_bot(&mut tk, x, y, id, team);
+        self.states.insert(i, tk);
+        id
+    }
+
+    // put a base in the current tick, also for setting up games by hand
+    // unlike init_state this leaves the walls alone
+    pub fn spawn_base(&mut self, x: Fx, y: Fx, team: i32) -> u32 {
+        let id = self.objidcntr;
+        let curtick = self.curtick;
+        let tk = self.states.iter_mut().find(|t| t.tick == curtick).expect("Could not find curtick in states vector");
+        if team < 0 || team as usize >= tk.teambotcount.len() {
+            panic!("Tried to spawn a base on a team that doesn't exist");
+        }
+        if x.tile() >= self.map.w || y.tile() >= self.map.h {
+            panic!("Tried to spawn a base off the map");
+        }
+
+        Game::add_base(tk, x, y, id, team);
+        self.objidcntr += 1;
+        id
+    }
+
+    fn init_state(&mut self, nteams: u32) {
+        // fill in the inital state and game map
+        if self.states.len() != 1 || self.curtick != 0 {
+            panic!("Tried to init state on a game that already started");
+        }
+        let mut tk = self.states.pop().unwrap();
+
+        // for now spawn a bunch of bots all across the map
+        let mut rng = XorShiftRng::seed_from_u64((self.baseseed) as u64);
+
+        // add a couple of random walls
+        for _ in 0..9 {
+            let x: u32 = rng.gen_range(0, self.map.w - 1);
+            let ystart: u32 = rng.gen_range(0,self.map.h - 30);
+            let yend: u32 = rng.gen_range(ystart+1, self.map.h);
+            for y in ystart..yend {
+                self.map.set_tile(x, y, MapTiles::Wall);
+            }
+        }
+
+        for _ in 0..9 {
+            let y: u32 = rng.gen_range(0, self.map.w - 1);
+            let xstart: u32 = rng.gen_range(0,self.map.w - 30);
+            let xend: u32 = rng.gen_range(xstart+1, self.map.w);
+            for x in xstart..xend {
+                self.map.set_tile(x, y, MapTiles::Wall);
+            }
+        }
+
+        // a base for each team at its starting spot, with nothing in the way
+        let mut bases = Vec::new();
+        for team in 0..nteams {
+            let (x, y) = self.team_start(team, nteams);
+            for ty in y.tile().saturating_sub(BASERAD)..(y.tile() + BASERAD + 1).min(self.map.h) {
+                for tx in x.tile().saturating_sub(BASERAD)..(x.tile() + BASERAD + 1).min(self.map.w) {
+                    self.map.set_tile(tx, ty, MapTiles::Unk);
+                }
+            }
+            bases.push(Marker{team, x, y});
+        }
+
+        // each team gets a bunch of bots around its starting spot
+        let spread = if nteams == 1 {
+            Fx::from_ratio(self.map.w.min(self.map.h) as i32, 3)
+        } else {
+            Fx::from_ratio(self.map.w.min(self.map.h) as i32, 8)
+        };
+        self.populate(&mut tk, nteams, &bases, &[], spread, &mut rng);
+
+        self.states.push(tk);
+    }
+
+    // a game on a loaded map instead of a random one
+    // every team needs a base on the map, markers for teams past nteams are left out
+    pub fn with_map(mf: &MapFile, nteams: u32, tickratio: u32, tickstep: Fx, seed: u32) -> Result<Game, MapError> {
+        mf.check_teams(nteams)?;
+        let mut game = Game::empty(mf.map.w, mf.map.h, nteams, tickratio, tickstep, seed);
+        game.map = mf.map.clone();
+
+        let bases: Vec<Marker> = mf.bases.iter().filter(|b| b.team < nteams).copied().collect();
+        let spawns: Vec<Marker> = mf.spawns.iter().filter(|s| s.team < nteams).copied().collect();
+        let mut tk = game.states.pop().unwrap();
+        let mut rng = XorShiftRng::seed_from_u64(seed as u64);
+        game.populate(&mut tk, nteams, &bases, &spawns, MAPSPREAD, &mut rng);
+        game.states.push(tk);
+        Ok(game)
+    }
+
+    // put in the bases, then each team's starting bots spread around its spawn points in turn
+    // or around its bases if it has no spawn points
+    fn populate(&mut self, tk: &mut GameTick, nteams: u32, bases: &[Marker], spawns: &[Marker], spread: Fx, rng: &mut XorShiftRng) {
+        for b in bases.iter() {
+            let id = self.objidcntr;
+            self.objidcntr += 1;
+            Game::add_base(tk, b.x, b.y, id, b.team as i32);
+        }
+
+        // random values come out as raw fixed point bits, the rng is all integer math
+        let spread = spread.to_bits();
+        let maxx = Fx::from_int(self.map.w as i32) - Fx::from_bits(1);
+        let maxy = Fx::from_int(self.map.h as i32) - Fx::from_bits(1);
+        for team in 0..nteams {
+            let mut spots: Vec<&Marker> = spawns.iter().filter(|s| s.team == team).collect();
+            if spots.is_empty() {
+                spots = bases.iter().filter(|b| b.team == team).collect();
+            }
+            for i in 0..STARTBOTS {
+                let spot = spots[(i as usize) % spots.len()];
+                let mut x = (spot.x + Fx::from_bits(rng.gen_range(-spread, spread))).max(Fx::ZERO).min(maxx);
+                let mut y = (spot.y + Fx::from_bits(rng.gen_range(-spread, spread))).max(Fx::ZERO).min(maxy);
+                // nobody starts out in a wall
+                if self.map.get_tile(x.tile(), y.tile()).blocks_move() {
+                    x = spot.x;
+                    y = spot.y;
+                }
+                self.add_bot(tk, x, y, self.objidcntr, team as i32);
+                self.objidcntr += 1;
+            }
+        }
+
+        // play around with starting velocities
+        for i in 0..tk.bots.len() {
+            tk.bots.vx[i] = Fx::from_bits(rng.gen_range(-BOTMAXVEL.to_bits(), BOTMAXVEL.to_bits()));
+            tk.bots.vy[i] = Fx::from_bits(rng.gen_range(-BOTMAXVEL.to_bits(), BOTMAXVEL.to_bits()));
+        }
+
+        log(&format!("starting out with {} bots", self.objidcntr)[..]);
+    }
+
+    // a netstep we already ran past on a guess gets rolled back and run again, if the guess was wrong
+    // and then we return the hash of the tick that started it, to ack with
+    // netsteps are expected in order, a guess is only fixed up once
+    pub fn add_netstep(&mut self, st: NetStep) -> Option<u64> {
+        let n = st.n;
+        let guessed = self.predicted.iter().position(|m| *m == n);
+
+        // the next netstep we will start, any before that are already done
+        let next = self.curtick.div_ceil(self.tickratio);
+        if (n < next && guessed.is_none()) || self.netsteps.iter().any(|s| s.n == n) {
+            log(&format!("Dropping old or duplicate netstep {}", n)[..]);
+            return None;
+        }
+
+        let wrong = !st.updates.is_empty();
+        let i = self.netsteps.iter().position(|s| s.n > n).unwrap_or(self.netsteps.len());
+        self.netsteps.insert(i, st);
+
+        self.predicted.remove(guessed?);
+        let start = n * self.tickratio;
+        if wrong {
+            self.rollback(start);
+        }
+        self.hash_at(start + 1)
+    }
+
+    // go back to the end of the given tick and run forward again to where we were
+    // with whatever netsteps we have now, guessing again for any still missing
+    pub fn rollback(&mut self, to: u32) {
+        if to >= self.curtick {
+            return;
+        }
+        let end = self.curtick;
+        let paints = self.paints_at(to).expect("Tried to roll back past the ticks we kept");
+
+        self.states.retain(|t| t.tick <= to);
+        self.curtick = to;
+        let tk = self.states.iter_mut().find(|t| t.tick == to).expect("Could not find rollback tick in states vector");
+        tk.paints = paints;
+        self.objidcntr = tk.nextid;
+        self.bottree = LocationGroups::new(self.map.w, self.map.h);
+        for b in tk.bots.iter() {
+            self.bottree.add_bot(b.id, b.x.tile(), b.y.tile());
+        }
+        self.flows.iter_mut().for_each(|f| f.invalidate());
+        let tickratio = self.tickratio;
+        self.predicted.retain(|m| m * tickratio < to);
+
+        while self.curtick < end {
+            if let TickResult::Waiting = self.tick() {
+                panic!("Ran out of netsteps running forward from a rollback");
+            }
+        }
+    }
+
+    // the server checks paint with this before sending it out
+    pub fn check_paint(&self, d: &PaintDelta) -> Result<(), PaintError> {
+        paint::check(&self.map, d)
+    }
+
+    // which paint layer a player paints on
+    pub fn player_team(&self, player: PlayerId) -> usize {
+        (player as usize) % self.states[0].teambotcount.len()
+    }
+
+    pub fn tick(&mut self) -> TickResult {
+        // every tickratio ticks we start a new netstep
+        // and we can't go on until we have that netstep's updates from the server
+        // unless we are allowed to guess, then we go on as if there was no paint and fix it up later
+        let mut netstep = None;
+        let mut res = TickResult::Ticked;
+        if self.curtick.is_multiple_of(self.tickratio) {
+            let n = self.curtick / self.tickratio;
+            match self.netsteps.iter().position(|st| st.n == n) {
+                Some(i) => {
+                    netstep = Some(i);
+                    res = TickResult::NetStep(n);
+                },
+                None if (self.predicted.len() as u32) < self.maxpredict => {
+                    self.predicted.push(n);
+                    res = TickResult::Predicted(n);
+                },
+                None => return TickResult::Waiting,
+            }
+        }
+
+        // the paint layers move over to the new tick instead of getting copied
+        let i = self.states.iter().position(|t| t.tick == self.curtick).expect("Could not find curtick in states vector");
+        let paints = std::mem::take(&mut self.states[i].paints);
+        let mut newtk: GameTick = self.states[i].clone();
+        newtk.paints = paints;
+        newtk.unpaint = Vec::new();
+        newtk.unfade = Vec::new();
+        newtk.tick += 1;
+
+        // old paint fades before new paint goes down
+        let amt = self.fade_at(newtk.tick);
+        if amt > 0 {
+            for (team, layer) in newtk.paints.iter_mut().enumerate() {
+                let gone = paint::fade(layer, amt);
+                self.flows[team].faded(amt, gone.as_ref());
+                if let Some(d) = gone {
+                    newtk.unfade.push((team, d));
+                }
+            }
+        }
+
+        // paint goes down before anything moves
+        if let Some(i) = netstep {
+            let st = &self.netsteps[i];
+            for u in st.updates.iter() {
+                if let Err(e) = self.check_paint(&u.delta) {
+                    log(&format!("Dropping paint from {} in netstep {}: {}", u.player, st.n, e)[..]);
+                    continue;
+                }
+                let team = self.player_team(u.player);
+                let cost = paint_cost(&u.delta);
+                if cost > newtk.ink[team] as u64 {
+                    log(&format!("Dropping paint from {} in netstep {}: not enough ink", u.player, st.n)[..]);
+                    continue;
+                }
+                newtk.ink[team] -= cost as u32;
+                let undo = paint::apply(&mut newtk.paints[team], &u.delta);
+                self.flows[team].painted(&u.delta, &undo);
+                newtk.unpaint.push((team, undo));
+            }
+        }
+        for (flow, layer) in self.flows.iter_mut().zip(newtk.paints.iter()) {
+            flow.update(&self.map, layer);
+        }
+
+        // get prng for this tick
+        let mut rng = XorShiftRng::seed_from_u64(self.baseseed.wrapping_add(newtk.tick) as u64);
+        
+        for i in 0..newtk.bots.len() {
+            // add random accel to each bot
+            let amt = Fx::from_bits(rng.gen_range(0, BOTRANDMAXACC.to_bits())) * self.tickstep;
+            let ang: u32 = rng.gen_range(0, fixed::ANGLES);
+
+            let xpart = fixed::cos(ang) * amt;
+            let ypart = fixed::sin(ang) * amt;
+
+            // work on a copy, and write it back once it has moved
+            let mut bt = newtk.bots.at(i);
+            let near = self.bottree.in_square(&newtk.bots, bt.x, bt.y, BOTCOLSZ);
+
+            bt.vx += xpart;
+            bt.vy += ypart;
+
+            // query paint for force
+            let st = steer::steer_around(&newtk.paints[bt.team as usize], &self.flows[bt.team as usize], bt.x, bt.y);
+            bt.vx += st.ax * self.tickstep;
+            bt.vy += st.ay * self.tickstep;
+            bt.vx -= bt.vx * st.hold;
+            bt.vy -= bt.vy * st.hold;
+
+            // push apart close bots
+            for id2 in near.iter() {
+                // don't do the same work twice
+                if *id2 <= bt.id {
+                    continue;
+                }
+
+                let j = newtk.bots.index(*id2).unwrap();
+                let mut bt2 = newtk.bots.at(j);
+
+                // test boids
+
+                let dx = bt.x - bt2.x;
+                let dy = bt.y - bt2.y;
+
+                if dx > Fx::ZERO && dx < BOTCOLSZ {
+                    if bt.vx < Fx::ZERO {
+                        bt.vx *= -BOTBOUNCEAMT;
+                    }
+                    if bt2.vx > Fx::ZERO {
+                        bt2.vx *= -BOTBOUNCEAMT;
+                    }
+                } else if dx < Fx::ZERO && dx > -BOTCOLSZ {
+                    if bt.vx > Fx::ZERO {
+                        bt.vx *= -BOTBOUNCEAMT;
+                    }
+                    if bt2.vx < Fx::ZERO {
+                        bt2.vx *= -BOTBOUNCEAMT;
+                    }
+                }
+
+                if dy > Fx::ZERO && dy < BOTCOLSZ {
+                    if bt.vy < Fx::ZERO {
+                        bt.vy *= -BOTBOUNCEAMT;
+                    }
+                    if bt2.vy > Fx::ZERO {
+                        bt2.vy *= -BOTBOUNCEAMT;
+                    }
+                } else if dy < Fx::ZERO && dy > -BOTCOLSZ {
+                    if bt.vy > Fx::ZERO {
+                        bt.vy *= -BOTBOUNCEAMT;
+                    }
+                    if bt2.vy < Fx::ZERO {
+                        bt2.vy *= -BOTBOUNCEAMT;
+                    }
+                }
+                newtk.bots.set(j, &bt2);
+            }
+
+            // max out vel
+            // just use a simple P-Inf norm, instead of doing any sqrt for now
+            bt.vx = bt.vx.clamp(-BOTMAXVEL, BOTMAXVEL);
+            bt.vy = bt.vy.clamp(-BOTMAXVEL, BOTMAXVEL);
+            if self.map.get_tile(bt.x.tile(), bt.y.tile()) == MapTiles::Mud {
+                bt.vx = bt.vx.clamp(-MUDMAXVEL, MUDMAXVEL);
+                bt.vy = bt.vy.clamp(-MUDMAXVEL, MUDMAXVEL);
+            }
+
+            // step vel for each bot
+            let mut newx = bt.x + (bt.vx * self.tickstep);
+            let mut newy = bt.y + (bt.vy * self.tickstep);
+
+            // bounce off edge
+            // don't have to be as complicated as walls
+            // just don't move, and bounce velocity
+            if newx <= Fx::ZERO || newx.tile() >= self.map.w {
+                newx = bt.x;
+                bt.vx *= -BOTBOUNCEAMT;
+            }
+            if newy <= Fx::ZERO || newy.tile() >= self.map.h {
+                newy = bt.y;
+                bt.vy *= -BOTBOUNCEAMT;
+            }
+
+            // bounce off walls, and anything else we can't go through
+            // go through every tile on the way, so nothing thin gets skipped over at high speed
+            // the axis that hit stays put, then what is left of the move gets checked again
+            // after two tries every axis that hit is back where it started
+            // a bot that started out stuck in something can move out of it, but never into anything else
+            let oix = bt.x.tile();
+            let oiy = bt.y.tile();
+            for _ in 0..2 {
+                let hit = match self.map.sweep(bt.x, bt.y, newx, newy) {
+                    Some(h) => h,
+                    None => break,
+                };
+                if hit.x {
+                    newx = bt.x;
+                    bt.vx *= -BOTBOUNCEAMT;
+                }
+                if hit.y {
+                    newy = bt.y;
+                    bt.vy *= -BOTBOUNCEAMT;
+                }
+            }
+
+            // move the bot
+            self.bottree.move_bot(bt.id, oix, oiy, newx.tile(), newy.tile());
+            bt.x = newx;
+            bt.y = newy;
+            newtk.bots.set(i, &bt);
+        }
+
+        self.fight(&mut newtk);
+
+        // bases spawn new bots once their timer is up, they start moving next tick
+        let now = self.game_time(newtk.tick);
+        for i in 0..newtk.bases.len() {
+            let b = newtk.bases[i];
+            if now < b.nextspawn {
+                continue;
+            }
+            newtk.bases[i].nextspawn = b.nextspawn + BASESPAWNTIME;
+            if newtk.teambotcount[b.team as usize] >= MAXTEAMBOTS {
+                continue;
+            }
+
+            // somewhere on the base, so they don't all stack up
+            let off = Fx::from_int(BASERAD as i32 / 2).to_bits();
+            let mut x = b.x + Fx::from_bits(rng.gen_range(-off, off));
+            let mut y = b.y + Fx::from_bits(rng.gen_range(-off, off));
+            if x.tile() >= self.map.w || y.tile() >= self.map.h || self.map.get_tile(x.tile(), y.tile()).blocks_move() {
+                x = b.x;
+                y = b.y;
+            }
+            let id = self.objidcntr;
+            self.objidcntr += 1;
+            self.add_bot(&mut newtk, x, y, id, b.team);
+            newtk.teamstats[b.team as usize].spawned += 1;
+        }
+
+        self.regen_ink(&mut newtk);
+
+        if newtk.over.is_none() {
+            newtk.over = self.check_over(&newtk);
+            if let Some(r) = &newtk.over {
+                log(&format!("Game over at tick {}, winner {:?}", r.tick, r.winner)[..]);
+            }
+        }
+
+        self.states.insert(0, newtk);
+        self.curtick += 1;
+
+        res
+    }
+
+    // drop ticks before the given one, they aren't needed for drawing anymore
+    // anything we might still have to roll back to is kept
+    pub fn prune(&mut self, before: u32) {
+        let mut before = before.min(self.curtick);
+        if let Some(n) = self.predicted.first() {
+            before = before.min(n * self.tickratio);
+        }
+        self.states.retain(|t| t.tick >= before);
+        let tickratio = self.tickratio;
+        self.netsteps.retain(|st| st.n * tickratio >= before);
+    }
+
+    // the last tick that isn't built on a guess, a rollback never redoes it or anything before it
+    pub fn confirmed_tick(&self) -> u32 {
+        match self.predicted.first() {
+            Some(n) => self.curtick.min(n * self.tickratio),
+            None => self.curtick,
+        }
+    }
+
+    // the state hash at the end of an older tick, None if we don't have it anymore
+    pub fn hash_at(&self, tick: u32) -> Option<u64> {
+        let paints = self.paints_at(tick)?;
+        let tk = self.states.iter().find(|t| t.tick == tick)?;
+        Some(tk.hash_with(&paints))
+    }
+
+    // the paint layers as they were at the end of a tick, by stepping back from the current ones
+    // None if we don't have every tick since then anymore
+    pub fn paints_at(&self, tick: u32) -> Option<Vec<GameMap>> {
+        if tick > self.curtick {
+            return None;
+        }
+        let mut paints = self.get_cur_tick().paints.clone();
+        for t in ((tick + 1)..=self.curtick).rev() {
+            let tk = self.states.iter().find(|s| s.tick == t)?;
+            // undo in the opposite order they went down
+            for (team, d) in tk.unpaint.iter().rev() {
+                paint::apply(&mut paints[*team], d);
+            }
+            let amt = self.fade_at(t);
+            if amt > 0 {
+                for (team, layer) in paints.iter_mut().enumerate() {
+                    let gone = tk.unfade.iter().find(|(t, _)| *t == team).map(|(_, d)| d);
+                    paint::unfade(layer, amt, gone);
+                }
+            }
+        }
+        Some(paints)
+    }
+
+    // the game is over once there is at most one team with bots or a base left
+    // a game with one team goes until it has nothing left
+    fn check_over(&self, tk: &GameTick) -> Option<GameResult> {
+        let nteams = tk.teambotcount.len();
+        let bases = |t: usize| tk.bases.iter().filter(|b| b.team as usize == t).count() as u32;
+        let alive: Vec<usize> = (0..nteams).filter(|t| tk.teambotcount[*t] > 0 || bases(*t) > 0).collect();
+        if alive.len() > 1 || (nteams == 1 && !alive.is_empty()) {
+            return None;
+        }
+
+        Some(GameResult {
+            tick: tk.tick,
+            winner: if nteams > 1 { alive.first().map(|t| *t as u32) } else { None },
+            teams: (0..nteams).map(|t| TeamResult {
+                bots: tk.teambotcount[t],
+                bases: bases(t),
+                spawned: tk.teamstats[t].spawned,
+                lost: tk.teamstats[t].lost,
+            }).collect(),
+        })
+    }
+
+    // how the game ended, if it has
+    pub fn game_over(&self) -> Option<&GameResult> {
+        self.get_cur_tick().over.as_ref()
+    }
+
+    // game seconds at the start of a tick
+    // saturates after about 9 hours of game time, well past any real game
+    pub fn game_time(&self, tick: u32) -> Fx {
+        let t = (self.tickstep.to_bits() as i64) * (tick as i64);
+        Fx::from_bits(t.min(i32::MAX as i64) as i32)
+    }
+
+    // how much paint fades over the tick that ends at the given one
+    // worked out from the total since the start, so the fractions add up the same on every client
+    pub fn fade_at(&self, tick: u32) -> u8 {
+        let total = |t: u32| ((self.decay as u64) * (self.tickstep.to_bits() as u64) * (t as u64)) >> fixed::FRACBITS;
+        (total(tick) - total(tick.saturating_sub(1))).min(0xff) as u8
+    }
+
+    pub fn get_cur_tick(&self) -> &GameTick {
+        let mut i = 0;
+        loop {
+            if i >= self.states.len() {
+                panic!("Could not find curtick in states vector");
+            }
+
+            let t = &self.states[i];
+            if t.tick == self.curtick {
+                return t;
+            }
+
+            i += 1;
+        };
+    }
+}
@@ -0,0 +1,109 @@
+// checking and applying paint from players
+// the server checks paint before it goes out in a netstep
+// and every client checks again before applying, so a bad update is dropped the same way everywhere
+
+use std::fmt;
+use stratapaint_protocol::{PaintDelta, Run};
+
+use super::{GameMap, Px};
+use super::steer::Command;
+
+// most pixels a single update can cover
+pub const MAXPAINTAREA: u64 = 1 << 16;
+
+#[derive(Debug,PartialEq,Eq)]
+pub enum PaintError {
+    Empty,
+    OutOfBounds,
+    TooBig,
+    BadColor(u32),
+}
+
+impl fmt::Display for PaintError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PaintError::Empty => write!(f, "paint doesn't change anything"),
+            PaintError::OutOfBounds => write!(f, "paint is off the map"),
+            PaintError::TooBig => write!(f, "paint covers too much of the map at once"),
+            PaintError::BadColor(c) => write!(f, "paint color {:08x} isn't a command", c),
+        }
+    }
+}
+
+pub fn check(map: &GameMap, d: &PaintDelta) -> Result<(), PaintError> {
+    if d.is_empty() {
+        return Err(PaintError::Empty);
+    }
+    if ((d.x as u64) + (d.w as u64) > map.w as u64) || ((d.y as u64) + (d.h as u64) > map.h as u64) {
+        return Err(PaintError::OutOfBounds);
+    }
+    if (d.w as u64) * (d.h as u64) > MAXPAINTAREA {
+        return Err(PaintError::TooBig);
+    }
+    // only commands, or clearing paint away
+    for r in d.runs.iter() {
+        if let Run::Fill(_, c) = *r {
+            let p = Px::from(c);
+            if p.a != 0 && Command::from_px(p).is_none() {
+                return Err(PaintError::BadColor(c));
+            }
+        }
+    }
+    Ok(())
+}
+
+// d must have passed check for this layer's map
+// returns a delta that puts back what d painted over, so history only has to keep what changed
+pub fn apply(layer: &mut GameMap, d: &PaintDelta) -> PaintDelta {
+    let mut old = vec![None; (d.w as usize) * (d.h as usize)];
+    d.for_each(|x, y, color| {
+        old[((x - d.x) + ((y - d.y) * d.w)) as usize] = Some(u32::from(layer.get(x, y)));
+        layer.set(x, y, Px::from(color));
+    });
+    PaintDelta::from_pixels(d.x, d.y, d.w, d.h, &old)
+}
+
+// take amt off the strength of every painted pixel in the layer, clearing anything that runs out
+// returns a delta of what got cleared, so unfade can put it back
+pub fn fade(layer: &mut GameMap, amt: u8) -> Option<PaintDelta> {
+    let mut gone = Vec::new();
+    for (i, p) in layer.data.iter_mut().enumerate() {
+        if p.a == 0 {
+            continue;
+        }
+        if p.a <= amt {
+            gone.push((i as u32, u32::from(*p)));
+            *p = Px::CLEAR;
+        } else {
+            p.a -= amt;
+        }
+    }
+    if gone.is_empty() {
+        return None;
+    }
+
+    // only as big as what went away
+    let w = layer.w;
+    let (mut x0, mut x1) = (w, 0);
+    let (y0, y1) = (gone[0].0 / w, gone[gone.len() - 1].0 / w);
+    for (i, _) in gone.iter() {
+        x0 = x0.min(i % w);
+        x1 = x1.max(i % w);
+    }
+    let dw = x1 - x0 + 1;
+    let mut old = vec![None; (dw * (y1 - y0 + 1)) as usize];
+    for (i, c) in gone.iter() {
+        old[((i % w - x0) + (i / w - y0) * dw) as usize] = Some(*c);
+    }
+    Some(PaintDelta::from_pixels(x0, y0, dw, y1 - y0 + 1, &old))
+}
+
+// undo a fade, given what it cleared
+pub fn unfade(layer: &mut GameMap, amt: u8, gone: Option<&PaintDelta>) {
+    for p in layer.data.iter_mut().filter(|p| p.a != 0) {
+        p.a += amt;
+    }
+    if let Some(d) = gone {
+        apply(layer, d);
+    }
+}
@@ -0,0 +1,221 @@
+// the full state of a game at its current tick
+// the server sends this to players joining late, or that have fallen out of sync
+// it goes over the network as opaque bytes, packed with the protocol's wire helpers
+
+use stratapaint_protocol::wire::{self, Reader};
+use stratapaint_protocol::{DecodeError, GameResult, PaintDelta};
+
+use super::{Game, GameTick, GameMap, Bots, BotState, BaseState, TeamStats, LocationGroups, FlowField, Px, Fx, MAXMAPDIM, MAXTEAMS, MAXINK};
+
+fn put_fx(buf: &mut Vec<u8>, v: Fx) {
+    wire::put_u32(buf, v.to_bits() as u32);
+}
+
+fn get_fx(rd: &mut Reader, name: &'static str) -> Result<Fx, DecodeError> {
+    Ok(Fx::from_bits(rd.u32(name)? as i32))
+}
+
+// maps go as a delta over the whole map, skipping clear pixels
+fn put_map(buf: &mut Vec<u8>, m: &GameMap) {
+    let px: Vec<Option<u32>> = m.data.iter().map(|p| {
+        if *p == Px::CLEAR {
+            None
+        } else {
+            Some(u32::from(*p))
+        }
+    }).collect();
+    PaintDelta::from_pixels(0, 0, m.w, m.h, &px).encode(buf);
+}
+
+fn get_map(rd: &mut Reader, name: &'static str) -> Result<GameMap, DecodeError> {
+    let d = PaintDelta::decode(rd)?;
+    if d.x != 0 || d.y != 0 || d.w > MAXMAPDIM || d.h > MAXMAPDIM {
+        return Err(DecodeError::BadField(name));
+    }
+
+    let mut m = GameMap::new(d.w, d.h);
+    d.for_each(|x, y, c| m.set(x, y, Px::from(c)));
+    Ok(m)
+}
+
+impl Game {
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let tk = self.get_cur_tick();
+
+        wire::put_varint(&mut buf, self.tickratio);
+        put_fx(&mut buf, self.tickstep);
+        wire::put_varint(&mut buf, self.decay);
+        wire::put_varint(&mut buf, self.curtick);
+        wire::put_varint(&mut buf, self.baseseed);
+        wire::put_varint(&mut buf, self.objidcntr);
+        put_map(&mut buf, &self.map);
+
+        wire::put_varint(&mut buf, tk.bases.len() as u32);
+        for b in tk.bases.iter() {
+            wire::put_varint(&mut buf, b.id);
+            put_fx(&mut buf, b.health);
+            put_fx(&mut buf, b.nextspawn);
+            put_fx(&mut buf, b.x);
+            put_fx(&mut buf, b.y);
+            wire::put_varint(&mut buf, b.team as u32);
+        }
+
+        wire::put_varint(&mut buf, tk.bots.len() as u32);
+        for b in tk.bots.iter() {
+            wire::put_varint(&mut buf, b.id);
+            put_fx(&mut buf, b.health);
+            put_fx(&mut buf, b.x);
+            put_fx(&mut buf, b.y);
+            put_fx(&mut buf, b.vx);
+            put_fx(&mut buf, b.vy);
+            wire::put_varint(&mut buf, b.team as u32);
+            wire::put_varint(&mut buf, b.cur_target);
+        }
+
+        wire::put_varint(&mut buf, tk.teambotcount.len() as u32);
+        for (i, (c, st)) in tk.teambotcount.iter().zip(tk.teamstats.iter()).enumerate() {
+            wire::put_varint(&mut buf, *c);
+            wire::put_varint(&mut buf, st.spawned);
+            wire::put_varint(&mut buf, st.lost);
+            wire::put_varint(&mut buf, tk.ink[i]);
+        }
+
+        wire::put_varint(&mut buf, tk.paints.len() as u32);
+        for p in tk.paints.iter() {
+            put_map(&mut buf, p);
+        }
+
+        match &tk.over {
+            Some(r) => {
+                wire::put_u8(&mut buf, 1);
+                r.encode(&mut buf);
+            },
+            None => wire::put_u8(&mut buf, 0),
+        }
+
+        buf
+    }
+
+    pub fn from_snapshot(b: &[u8]) -> Result<Game, DecodeError> {
+        let mut rd = Reader::new(b);
+
+        let tickratio = rd.varint("tickratio")?;
+        if tickratio == 0 {
+            return Err(DecodeError::BadField("tickratio"));
+        }
+        let tickstep = get_fx(&mut rd, "tickstep")?;
+        if tickstep <= Fx::ZERO {
+            return Err(DecodeError::BadField("tickstep"));
+        }
+        let decay = rd.varint("decay")?;
+        let curtick = rd.varint("curtick")?;
+        let baseseed = rd.varint("seed")?;
+        let objidcntr = rd.varint("objidcntr")?;
+        let map = get_map(&mut rd, "map")?;
+
+        let mut tk = GameTick {
+            tick: curtick,
+            bases: Vec::new(),
+            bots: Bots::new(),
+            teambotcount: Vec::new(),
+            teamstats: Vec::new(),
+            ink: Vec::new(),
+            paints: Vec::new(),
+            unpaint: Vec::new(),
+            unfade: Vec::new(),
+            over: None,
+            nextid: objidcntr,
+        };
+
+        let n = rd.count(1, "bases")?;
+        for _ in 0..n {
+            tk.bases.push(BaseState {
+                id: rd.varint("base id")?,
+                health: get_fx(&mut rd, "base health")?,
+                nextspawn: get_fx(&mut rd, "base nextspawn")?,
+                x: get_fx(&mut rd, "base x")?,
+                y: get_fx(&mut rd, "base y")?,
+                team: rd.varint("base team")? as i32,
+            });
+        }
+
+        let mut bottree = LocationGroups::new(map.w, map.h);
+        let n = rd.count(1, "bots")?;
+        for _ in 0..n {
+            let b = BotState {
+                id: rd.varint("bot id")?,
+                health: get_fx(&mut rd, "bot health")?,
+                x: get_fx(&mut rd, "bot x")?,
+                y: get_fx(&mut rd, "bot y")?,
+                vx: get_fx(&mut rd, "bot vx")?,
+                vy: get_fx(&mut rd, "bot vy")?,
+                team: rd.varint("bot team")? as i32,
+                cur_target: rd.varint("bot target")?,
+            };
+            if tk.bots.contains(b.id) {
+                return Err(DecodeError::BadField("bot id"));
+            }
+            if b.x.tile() >= map.w || b.y.tile() >= map.h {
+                return Err(DecodeError::BadField("bot position"));
+            }
+            bottree.add_bot(b.id, b.x.tile(), b.y.tile());
+            tk.bots.insert(b);
+        }
+
+        let n = rd.count(1, "teams")?;
+        if n == 0 || n > MAXTEAMS as usize {
+            return Err(DecodeError::BadField("teams"));
+        }
+        for _ in 0..n {
+            tk.teambotcount.push(rd.varint("team bot count")?);
+            tk.teamstats.push(TeamStats {
+                spawned: rd.varint("team spawned")?,
+                lost: rd.varint("team lost")?,
+            });
+            let ink = rd.varint("team ink")?;
+            if ink > MAXINK {
+                return Err(DecodeError::BadField("team ink"));
+            }
+            tk.ink.push(ink);
+        }
+
+        // a paint layer for every team
+        if rd.count(1, "paints")? != n {
+            return Err(DecodeError::BadField("paints"));
+        }
+        for _ in 0..n {
+            let p = get_map(&mut rd, "paint")?;
+            if p.w != map.w || p.h != map.h {
+                return Err(DecodeError::BadField("paint"));
+            }
+            tk.paints.push(p);
+        }
+        if tk.bases.iter().any(|b| b.team as usize >= n) || tk.bots.team.iter().any(|t| *t as usize >= n) {
+            return Err(DecodeError::BadField("team"));
+        }
+
+        tk.over = match rd.u8("over")? {
+            0 => None,
+            1 => Some(GameResult::decode(&mut rd)?),
+            _ => return Err(DecodeError::BadField("over")),
+        };
+        rd.done()?;
+
+        Ok(Game {
+            bottree,
+            flows: (0..n).map(|_| FlowField::new(map.w, map.h)).collect(),
+            states: vec![tk],
+            tickratio,
+            tickstep,
+            curtick,
+            map,
+            baseseed,
+            objidcntr,
+            maxpredict: 0,
+            decay,
+            netsteps: Vec::new(),
+            predicted: Vec::new(),
+        })
+    }
+}
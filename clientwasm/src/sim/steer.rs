@@ -0,0 +1,149 @@
+// turning a team's paint layer into steering for its bots
+// paint is how players command their bots, each color is a different command
+// and the alpha is how strong it is
+//
+// paint colors (0xRRGGBBAA)
+//  attract    ff 00 00 aa  bots up to STEERRAD away are pulled toward it
+//  repel      00 00 ff aa  bots up to STEERRAD away are pushed away from it
+//  hold       00 ff 00 aa  bots on it slow down and stay put
+//  direction  ff dd ff aa  bots on it are pushed toward angle dd, in 256ths of a turn
+// a pixel with zero alpha is unpainted, any other color isn't a command and isn't allowed
+// bots with no attract paint in range head for the closest there is, the long way around walls, see flow.rs
+
+use super::{GameMap, FlowField, Px, Fx, fixed};
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Command {
+    Attract,
+    Repel,
+    Hold,
+    Dir(u8),
+}
+
+impl Command {
+    // the command and its strength, None for unpainted or unknown colors
+    pub fn from_px(p: Px) -> Option<(Command, u8)> {
+        if p.a == 0 {
+            return None;
+        }
+        let cmd = match (p.r, p.g, p.b) {
+            (0xff, 0, 0) => Command::Attract,
+            (0, 0, 0xff) => Command::Repel,
+            (0, 0xff, 0) => Command::Hold,
+            (0xff, d, 0xff) => Command::Dir(d),
+            _ => return None,
+        };
+        Some((cmd, p.a))
+    }
+
+    pub fn to_px(self, strength: u8) -> Px {
+        let (r, g, b) = match self {
+            Command::Attract => (0xff, 0, 0),
+            Command::Repel => (0, 0, 0xff),
+            Command::Hold => (0, 0xff, 0),
+            Command::Dir(d) => (0xff, d, 0xff),
+        };
+        Px{r, g, b, a: strength}
+    }
+}
+
+// steering constants
+pub const STEERRAD: i32 = 4; // in map tiles
+const ATTRACTACC: Fx = Fx::from_int(14); // at full strength, per game second
+const DIRACC: Fx = Fx::from_int(10);
+const HOLDDAMP: Fx = Fx::from_ratio(1, 2); // part of velocity lost per tick at full strength
+
+// what the paint around a bot wants it to do
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub struct Steer {
+    pub ax: Fx, // acceleration, per game second
+    pub ay: Fx,
+    pub hold: Fx, // part of the velocity to drop this tick
+}
+
+// strength (0-255) of a full strength value
+fn scale(v: Fx, strength: i64) -> Fx {
+    Fx::from_bits(((v.to_bits() as i64) * strength / 0xff) as i32)
+}
+
+pub fn steer(layer: &GameMap, x: Fx, y: Fx) -> Steer {
+    steer_near(layer, x, y).0
+}
+
+// steer by the paint around the bot, or by the flow field when none of it is attract paint
+pub fn steer_around(layer: &GameMap, flow: &FlowField, x: Fx, y: Fx) -> Steer {
+    let (mut s, attracted) = steer_near(layer, x, y);
+    if attracted {
+        return s;
+    }
+    if let Some((gx, gy, strength)) = flow.downhill(x.floor(), y.floor()) {
+        let m = gx.abs().max(gy.abs());
+        let strength = strength as i64;
+        s.ax += scale(Fx::from_bits(((ATTRACTACC.to_bits() as i64) * gx / m) as i32), strength);
+        s.ay += scale(Fx::from_bits(((ATTRACTACC.to_bits() as i64) * gy / m) as i32), strength);
+    }
+    s
+}
+
+// and if there was any attract paint in range
+fn steer_near(layer: &GameMap, x: Fx, y: Fx) -> (Steer, bool) {
+    let mut s = Steer::default();
+    let tx = x.floor();
+    let ty = y.floor();
+    let half = Fx::from_ratio(1, 2);
+
+    // attract and repel pull toward (or away from) the weighted middle of all the paint in range
+    // kept in raw bits in i64, a full window of paint would overflow Fx
+    let mut sumx: i64 = 0;
+    let mut sumy: i64 = 0;
+    let mut weight: i64 = 0;
+    let mut attracted = false;
+
+    for py in (ty - STEERRAD)..=(ty + STEERRAD) {
+        if py < 0 || py >= layer.h as i32 {
+            continue;
+        }
+        for px in (tx - STEERRAD)..=(tx + STEERRAD) {
+            if px < 0 || px >= layer.w as i32 {
+                continue;
+            }
+            let (cmd, strength) = match Command::from_px(layer.get(px as u32, py as u32)) {
+                Some(c) => c,
+                None => continue,
+            };
+            let strength = strength as i64;
+
+            match cmd {
+                Command::Attract | Command::Repel => {
+                    let sign = if cmd == Command::Attract { 1 } else { -1 };
+                    let ox = (Fx::from_int(px) + half - x).to_bits() as i64;
+                    let oy = (Fx::from_int(py) + half - y).to_bits() as i64;
+                    sumx += sign * ox * strength;
+                    sumy += sign * oy * strength;
+                    weight += strength;
+                    attracted |= cmd == Command::Attract;
+                },
+                // these only do anything under the bot
+                Command::Hold if px == tx && py == ty => {
+                    s.hold = scale(HOLDDAMP, strength);
+                },
+                Command::Dir(d) if px == tx && py == ty => {
+                    let ang = (d as u32) * (fixed::ANGLES / 256);
+                    s.ax += scale(fixed::cos(ang) * DIRACC, strength);
+                    s.ay += scale(fixed::sin(ang) * DIRACC, strength);
+                },
+                _ => (),
+            }
+        }
+    }
+
+    // same P-Inf norm as the max velocity, full strength once there is a pixel's worth of full paint
+    let m = sumx.abs().max(sumy.abs());
+    if m > 0 {
+        let strength = weight.min(0xff);
+        s.ax += scale(Fx::from_bits(((ATTRACTACC.to_bits() as i64) * sumx / m) as i32), strength);
+        s.ay += scale(Fx::from_bits(((ATTRACTACC.to_bits() as i64) * sumy / m) as i32), strength);
+    }
+
+    (s, attracted)
+}
@@ -0,0 +1,57 @@
+// bases put new bots into the game on a timer
+use clientwasm::sim::{Game, Fx, GROUPSHIFT, BASERAD};
+
+mod common;
+use common::run_netstep;
+
+#[test]
+fn a_base_per_team() {
+    let game = Game::new(300, 300, 2, 4, Fx::from_ratio(1, 10), 5);
+    let tk = game.get_cur_tick();
+    assert_eq!(tk.bases.len(), 2);
+    for (t, b) in tk.bases.iter().enumerate() {
+        assert_eq!(b.team, t as i32);
+        assert_eq!((b.x, b.y), game.team_start(t as u32, 2));
+    }
+}
+
+#[test]
+fn bases_spawn_bots() {
+    let mut game = Game::new(300, 300, 2, 4, Fx::from_ratio(1, 10), 5);
+    let start = game.get_cur_tick().teambotcount.clone();
+    let firstid = game.objidcntr;
+
+    // 2 game seconds, the timer is half a second
+    for n in 0..5 {
+        run_netstep(&mut game, n, Vec::new());
+    }
+
+    let tk = game.get_cur_tick();
+    for (now, before) in tk.teambotcount.iter().zip(start.iter()) {
+        assert_eq!(*now, before + 4);
+    }
+    assert_eq!(tk.bots.len() as u32, tk.teambotcount.iter().sum::<u32>());
+
+    // new bots get the next ids, start on their base, and are in the location groups
+    assert_eq!(game.objidcntr, firstid + 8);
+    for id in firstid..game.objidcntr {
+        let b = tk.bots.get(id).unwrap();
+        let base = tk.bases[b.team as usize];
+        let far = Fx::from_int(BASERAD as i32 + 2);
+        assert!((b.x - base.x).abs() < far && (b.y - base.y).abs() < far);
+
+        let g = (b.x.tile() >> GROUPSHIFT) + ((b.y.tile() >> GROUPSHIFT) * game.bottree.groupw);
+        assert!(game.bottree.vecs[g as usize].contains(&id));
+    }
+}
+
+#[test]
+fn spawns_are_deterministic() {
+    let mut a = Game::new(300, 300, 3, 4, Fx::from_ratio(1, 10), 9);
+    let mut b = Game::new(300, 300, 3, 4, Fx::from_ratio(1, 10), 9);
+    for n in 0..8 {
+        run_netstep(&mut a, n, Vec::new());
+        run_netstep(&mut b, n, Vec::new());
+    }
+    assert_eq!(a.snapshot(), b.snapshot());
+}
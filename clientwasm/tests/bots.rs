@@ -0,0 +1,69 @@
+// the bot store keeps bots in id order, whatever order they come and go in
+use clientwasm::sim::{Bots, BotState, Fx};
+
+fn bot(id: u32) -> BotState {
+    BotState {
+        id,
+        health: Fx::from_int(100),
+        x: Fx::from_int(id as i32),
+        y: Fx::from_int(2 * id as i32),
+        vx: Fx::ZERO,
+        vy: Fx::ZERO,
+        team: (id % 2) as i32,
+        cur_target: 0,
+    }
+}
+
+fn ids(b: &Bots) -> Vec<u32> {
+    b.iter().map(|b| b.id).collect()
+}
+
+#[test]
+fn kept_in_id_order() {
+    let mut b = Bots::new();
+    for id in [4, 1, 9, 2, 7].iter() {
+        b.insert(bot(*id));
+    }
+    assert_eq!(ids(&b), vec![1, 2, 4, 7, 9]);
+    assert_eq!(b.len(), 5);
+    assert_eq!(b.index(7), Some(3));
+    assert_eq!(b.index(3), None);
+    assert!(b.contains(9));
+
+    // every field comes back where it went in
+    for id in [1, 2, 4, 7, 9].iter() {
+        let got = b.get(*id).unwrap();
+        assert_eq!((got.x, got.y, got.team), (bot(*id).x, bot(*id).y, bot(*id).team));
+    }
+}
+
+#[test]
+fn set_and_retain() {
+    let mut b = Bots::new();
+    for id in 1..=10 {
+        b.insert(bot(id));
+    }
+
+    let i = b.index(5).unwrap();
+    let mut five = b.at(i);
+    five.health = Fx::ZERO;
+    five.cur_target = 6;
+    b.set(i, &five);
+    assert_eq!(b.get(5).unwrap().cur_target, 6);
+
+    b.retain(|bt| bt.health > Fx::ZERO && bt.id % 3 != 0);
+    assert_eq!(ids(&b), vec![1, 2, 4, 7, 8, 10]);
+    assert_eq!(b.get(8).unwrap().y, Fx::from_int(16));
+
+    // new ids still go on the end
+    b.insert(bot(11));
+    assert_eq!(b.index(11), Some(6));
+}
+
+#[test]
+#[should_panic]
+fn no_duplicate_ids() {
+    let mut b = Bots::new();
+    b.insert(bot(3));
+    b.insert(bot(3));
+}
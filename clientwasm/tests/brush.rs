@@ -0,0 +1,117 @@
+// brushes come out as the same pixels everywhere, and survive the trip over the network
+use clientwasm::brush::{Brush, ERASE, FILLRAD};
+use clientwasm::sim::steer::Command;
+use clientwasm::sim::{Game, GameMap, MapTiles, Px, TickResult, Fx};
+use stratapaint_protocol::{NetStep, PaintDelta, PaintUpdate, ServerMsg};
+
+fn hold() -> u32 {
+    u32::from(Command::Hold.to_px(0xff))
+}
+
+fn count(d: &PaintDelta) -> u32 {
+    let mut n = 0;
+    d.for_each(|_, _, _| n += 1);
+    n
+}
+
+fn covers(d: &PaintDelta, x: u32, y: u32) -> bool {
+    let mut hit = false;
+    d.for_each(|px, py, _| hit |= px == x && py == y);
+    hit
+}
+
+#[test]
+fn circles_stay_on_the_map() {
+    let map = GameMap::new(50, 40);
+    let c = Brush::Circle{x: 25, y: 20, rad: 3}.paint(&map, hold()).unwrap();
+    assert_eq!((c.x, c.y, c.w, c.h), (22, 17, 7, 7));
+    assert_eq!(count(&c), 29);
+
+    // cut off at the corners
+    let c = Brush::Circle{x: 1, y: 2, rad: 4}.paint(&map, hold()).unwrap();
+    assert_eq!((c.x, c.y, c.w, c.h), (0, 0, 6, 7));
+    let c = Brush::Circle{x: 49, y: 39, rad: 4}.paint(&map, hold()).unwrap();
+    assert_eq!((c.x, c.y, c.w, c.h), (45, 35, 5, 5));
+    assert_eq!(Brush::Circle{x: 60, y: 60, rad: 4}.paint(&map, hold()), None);
+}
+
+#[test]
+fn lines_and_rects() {
+    let map = GameMap::new(50, 40);
+
+    // three wide, with round ends one past each end
+    let l = Brush::Line{points: vec![(10, 10), (20, 10)], width: 3}.paint(&map, hold()).unwrap();
+    assert_eq!(count(&l), 11 * 3 + 2 * 3);
+    assert!(covers(&l, 9, 9) && covers(&l, 21, 11) && !covers(&l, 8, 10) && !covers(&l, 15, 12));
+
+    // around a corner, and a diagonal one pixel wide has no gaps
+    let l = Brush::Line{points: vec![(10, 10), (20, 10), (20, 20)], width: 1}.paint(&map, hold()).unwrap();
+    assert_eq!(count(&l), 21);
+    assert!(covers(&l, 20, 15) && !covers(&l, 15, 15));
+    let l = Brush::Line{points: vec![(0, 0), (9, 9)], width: 1}.paint(&map, hold()).unwrap();
+    assert!((0..10).all(|i| covers(&l, i, i)));
+
+    let r = Brush::Rect{x: 45, y: 30, w: 20, h: 5}.paint(&map, hold()).unwrap();
+    assert_eq!((r.x, r.y, r.w, r.h), (45, 30, 5, 5));
+    assert_eq!(count(&r), 25);
+    assert_eq!(Brush::Rect{x: 3, y: 3, w: 0, h: 5}.paint(&map, hold()), None);
+}
+
+#[test]
+fn fill_stops_at_walls() {
+    // a walled in box, 9x9 inside
+    let mut map = GameMap::new(30, 30);
+    for i in 5..16 {
+        map.set_tile(i, 5, MapTiles::Wall);
+        map.set_tile(i, 15, MapTiles::Wall);
+        map.set_tile(5, i, MapTiles::Wall);
+        map.set_tile(15, i, MapTiles::Wall);
+    }
+    let inside = Brush::Fill{x: 10, y: 10}.paint(&map, hold()).unwrap();
+    assert_eq!(count(&inside), 81);
+    assert!(covers(&inside, 6, 6) && !covers(&inside, 5, 6));
+
+    let outside = Brush::Fill{x: 0, y: 0}.paint(&map, hold()).unwrap();
+    assert_eq!(count(&outside), 30 * 30 - 11 * 11);
+    assert_eq!(Brush::Fill{x: 5, y: 5}.paint(&map, hold()), None);
+
+    // and doesn't go on forever
+    let big = GameMap::new(600, 600);
+    let f = Brush::Fill{x: 300, y: 300}.paint(&big, hold()).unwrap();
+    assert_eq!((f.w, f.h), (2 * FILLRAD + 1, 2 * FILLRAD + 1));
+}
+
+#[test]
+fn replays_the_same_everywhere() {
+    let map = GameMap::new(100, 100);
+    let dir = u32::from(Command::Dir(40).to_px(0xff));
+    let strokes = vec![
+        Brush::Fill{x: 80, y: 10}.paint(&map, dir).unwrap(),
+        Brush::Rect{x: 10, y: 10, w: 30, h: 30}.paint(&map, hold()).unwrap(),
+        Brush::Line{points: vec![(5, 50), (60, 20), (90, 90)], width: 4}.paint(&map, hold()).unwrap(),
+        Brush::Circle{x: 25, y: 25, rad: 5}.paint(&map, ERASE).unwrap(),
+    ];
+
+    // sent to us, and to someone else through the wire
+    let st = NetStep{n: 0, updates: strokes.into_iter().map(|delta| PaintUpdate{player: 0, delta}).collect()};
+    let sent = match ServerMsg::decode(&ServerMsg::Step(st.clone()).encode()).unwrap() {
+        ServerMsg::Step(s) => s,
+        _ => panic!("wrong message back"),
+    };
+
+    let mut games = Vec::new();
+    for st in [st, sent].iter() {
+        let mut g = Game::empty(100, 100, 1, 4, Fx::from_ratio(1, 10), 2);
+        g.decay = 0;
+        g.add_netstep(st.clone());
+        while !matches!(g.tick(), TickResult::Waiting) {}
+        games.push(g);
+    }
+    assert_eq!(games[0].snapshot(), games[1].snapshot());
+
+    // the eraser took the middle out of the rect
+    let layer = &games[0].get_cur_tick().paints[0];
+    assert!(layer.get(25, 25) == Px::CLEAR);
+    assert!(layer.get(12, 12) == Command::Hold.to_px(0xff));
+    assert!(layer.get(80, 10) == Command::Dir(40).to_px(0xff));
+}
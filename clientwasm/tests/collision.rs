@@ -0,0 +1,127 @@
+// bots moving fast or at an angle still can't get through or stuck in walls
+use clientwasm::sim::steer::Command;
+use clientwasm::sim::{Game, GameMap, Hit, Fx};
+
+mod common;
+use common::{fx, paint_all, run_netstep, run_netstep_checked, walls};
+
+fn in_wall(game: &Game) -> bool {
+    game.get_cur_tick().bots.iter().any(|b| game.map.get_tile(b.x.tile(), b.y.tile()).blocks_move())
+}
+
+#[test]
+fn sweep_finds_first_wall() {
+    let mut map = GameMap::new(20, 20);
+    walls(&mut map, &[(5, 3), (8, 3), (12, 9), (13, 10)]);
+
+    // straight along, the near wall first
+    assert_eq!(map.sweep(fx(1.5), fx(3.5), fx(10.5), fx(3.5)), Some(Hit{x: true, y: false}));
+    assert_eq!(map.sweep(fx(5.5), fx(1.5), fx(5.5), fx(7.5)), Some(Hit{x: false, y: true}));
+    assert_eq!(map.sweep(fx(1.5), fx(4.5), fx(10.5), fx(4.5)), None);
+
+    // skipping right past the corner of a wall
+    assert_eq!(map.sweep(fx(4.0), fx(1.5), fx(6.0), fx(2.9)), None);
+
+    // diagonally through the gap between two walls touching at a corner
+    assert_eq!(map.sweep(fx(11.5), fx(11.5), fx(14.5), fx(8.5)), Some(Hit{x: true, y: false}));
+    assert_eq!(map.sweep(fx(12.5), fx(11.5), fx(12.5), fx(8.5)), Some(Hit{x: false, y: true}));
+    // the other way, the side across x is looked at first
+    assert_eq!(map.sweep(fx(14.5), fx(8.5), fx(11.5), fx(11.5)), Some(Hit{x: true, y: false}));
+
+    // starting in a wall only counts the walls after it
+    assert_eq!(map.sweep(fx(5.5), fx(3.5), fx(6.5), fx(3.5)), None);
+    assert_eq!(map.sweep(fx(5.5), fx(3.5), fx(9.5), fx(3.5)), Some(Hit{x: true, y: false}));
+}
+
+#[test]
+fn fast_bots_dont_tunnel() {
+    // a whole second a tick, so a bot at full speed goes 9 tiles each tick
+    let mut game = Game::empty(100, 100, 1, 4, Fx::from_int(1), 5);
+    game.decay = 0; // or the paint is gone before they get there
+    for y in 0..100 {
+        walls(&mut game.map, &[(60, y)]);
+    }
+    let ids: Vec<u32> = (0..20).map(|i| game.spawn_bot(fx(40.5 + i as f32 * 0.9), fx(5.5 + i as f32 * 4.3), 0)).collect();
+
+    // everything pushes right into the wall
+    let mut updates = paint_all(1, 0, 0, 100, 100, Command::Dir(0));
+    for n in 0..20 {
+        run_netstep_checked(&mut game, n, updates, |g| {
+            assert!(!in_wall(g), "bot in a wall at tick {}", g.curtick);
+            assert!(g.get_cur_tick().bots.iter().all(|b| b.x < Fx::from_int(60)), "bot tunneled at tick {}", g.curtick);
+        });
+        updates = Vec::new();
+    }
+
+    // and they got there
+    let tk = game.get_cur_tick();
+    assert!(ids.iter().all(|id| tk.bots.get(*id).unwrap().x > Fx::from_int(50)));
+}
+
+#[test]
+fn diagonal_into_corners() {
+    // an inside corner, and a diagonal line of walls with gaps only at the corners
+    let mut game = Game::empty(100, 100, 1, 4, Fx::from_int(1), 8);
+    for i in 0..30 {
+        walls(&mut game.map, &[(70, 40 + i), (40 + i, 70)]);
+        walls(&mut game.map, &[(10 + i, 35 - i)]);
+    }
+    let inside = game.spawn_bot(fx(55.5), fx(55.5), 0);
+    let stair = game.spawn_bot(fx(15.5), fx(15.5), 0);
+
+    // down and to the right, then up and to the left
+    for n in 0..30 {
+        let updates = match n {
+            0 => paint_all(1, 0, 0, 100, 100, Command::Dir(32)),
+            15 => paint_all(1, 0, 0, 100, 100, Command::Dir(160)),
+            _ => Vec::new(),
+        };
+        run_netstep_checked(&mut game, n, updates, |g| {
+            assert!(!in_wall(g), "bot in a wall at tick {}", g.curtick);
+            let tk = g.get_cur_tick();
+            let b = tk.bots.get(inside).unwrap();
+            assert!(b.x < Fx::from_int(70) && b.y < Fx::from_int(70), "bot got out the corner at tick {}", g.curtick);
+            // one side of the diagonal line is x + y < 45
+            let b = tk.bots.get(stair).unwrap();
+            assert!(b.x.tile() + b.y.tile() < 45, "bot got through the stairs at tick {}", g.curtick);
+        });
+    }
+}
+
+#[test]
+fn stuck_bots_dont_tunnel_either() {
+    // a bot inside a wall, with walls right next to it and clear ground past them
+    let mut game = Game::empty(40, 40, 1, 4, Fx::from_int(1), 9);
+    game.decay = 0;
+    for y in 0..40 {
+        walls(&mut game.map, &[(20, y), (21, y), (22, y)]);
+    }
+    let id = game.spawn_bot(fx(20.5), fx(20.5), 0);
+
+    // pushed right, into more wall, it never gets through to the far side
+    let mut updates = paint_all(1, 0, 0, 40, 40, Command::Dir(0));
+    for n in 0..10 {
+        run_netstep_checked(&mut game, n, updates, |g| {
+            let b = g.get_cur_tick().bots.get(id).unwrap();
+            assert!(b.x < Fx::from_int(21), "stuck bot got through at tick {}", g.curtick);
+        });
+        updates = Vec::new();
+    }
+
+    // pushed left, it gets out the side it can
+    let mut updates = paint_all(1, 0, 0, 40, 40, Command::Dir(128));
+    for n in 10..20 {
+        run_netstep(&mut game, n, updates);
+        updates = Vec::new();
+    }
+    assert!(game.get_cur_tick().bots.get(id).unwrap().x < Fx::from_int(20));
+}
+
+#[test]
+fn random_maps_stay_out_of_walls() {
+    let mut game = Game::new(200, 200, 2, 4, Fx::from_ratio(1, 2), 21);
+    assert!(!in_wall(&game));
+    for n in 0..40 {
+        run_netstep_checked(&mut game, n, Vec::new(), |g| assert!(!in_wall(g), "bot in a wall at tick {}", g.curtick));
+    }
+}
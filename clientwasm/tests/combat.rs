@@ -0,0 +1,98 @@
+// bots shoot enemies in range until they die
+use clientwasm::sim::steer::Command;
+use clientwasm::sim::{Game, Fx, GROUPSHIFT};
+
+mod common;
+use common::{fx, paint_all, run_netstep};
+
+fn in_groups(game: &Game, id: u32) -> bool {
+    game.bottree.vecs.iter().any(|v| v.contains(&id))
+}
+
+#[test]
+fn outnumbered_bot_dies() {
+    let mut game = Game::empty(100, 100, 2, 4, Fx::from_ratio(1, 10), 1);
+    let a1 = game.spawn_bot(fx(50.5), fx(50.5), 0);
+    let a2 = game.spawn_bot(fx(50.5), fx(52.5), 0);
+    let b = game.spawn_bot(fx(51.5), fx(50.5), 1);
+    let far = game.spawn_bot(fx(90.5), fx(90.5), 1);
+
+    run_netstep(&mut game, 0, paint_all(2, 40, 40, 20, 20, Command::Hold));
+    {
+        let tk = game.get_cur_tick();
+        let t = |id: u32| tk.bots.get(id).unwrap().cur_target;
+        assert_eq!(t(b), a1); // the closest one
+        assert_eq!(t(a1), b);
+        assert_eq!(t(a2), b);
+        assert_eq!(t(far), 0);
+    }
+
+    // two on one, 100 health at 20 a second each
+    for n in 1..8 {
+        run_netstep(&mut game, n, Vec::new());
+    }
+
+    let tk = game.get_cur_tick();
+    assert!(!tk.bots.contains(b));
+    assert!(!in_groups(&game, b));
+    assert_eq!(tk.teambotcount, vec![2, 1]);
+    assert_eq!(tk.bots.len(), 3);
+
+    // b only ever shot a1, and nobody is left to shoot
+    assert!(tk.bots.get(a1).unwrap().health < tk.bots.get(a2).unwrap().health);
+    assert_eq!(tk.bots.get(a1).unwrap().cur_target, 0);
+    assert_eq!(tk.bots.get(a2).unwrap().cur_target, 0);
+}
+
+#[test]
+fn one_on_one_to_the_death() {
+    let mut game = Game::empty(100, 100, 2, 4, Fx::from_ratio(1, 10), 1);
+    let a = game.spawn_bot(fx(20.5), fx(20.5), 0);
+    let b = game.spawn_bot(fx(22.5), fx(20.5), 1);
+
+    run_netstep(&mut game, 0, paint_all(2, 10, 10, 20, 20, Command::Hold));
+    for n in 1..14 {
+        run_netstep(&mut game, n, Vec::new());
+    }
+
+    // whoever started shooting first wins, and the loser is gone everywhere
+    let tk = game.get_cur_tick();
+    assert_eq!(tk.bots.len(), 1);
+    assert_eq!(tk.teambotcount.iter().sum::<u32>(), 1);
+    let (winner, loser) = if tk.bots.contains(a) { (a, b) } else { (b, a) };
+    assert!(in_groups(&game, winner));
+    assert!(!in_groups(&game, loser));
+    assert!(tk.bots.get(winner).unwrap().health > Fx::ZERO);
+}
+
+#[test]
+fn teammates_dont_fight() {
+    let mut game = Game::empty(100, 100, 2, 4, Fx::from_ratio(1, 10), 1);
+    let a = game.spawn_bot(fx(20.5), fx(20.5), 0);
+    let b = game.spawn_bot(fx(21.5), fx(20.5), 0);
+
+    run_netstep(&mut game, 0, paint_all(2, 10, 10, 20, 20, Command::Hold));
+    let tk = game.get_cur_tick();
+    assert_eq!(tk.bots.get(a).unwrap().cur_target, 0);
+    assert_eq!(tk.bots.get(b).unwrap().cur_target, 0);
+}
+
+#[test]
+fn groups_stay_sorted() {
+    let mut game = Game::new(300, 300, 2, 4, Fx::from_ratio(1, 10), 4);
+    for n in 0..20 {
+        run_netstep(&mut game, n, Vec::new());
+    }
+
+    let tk = game.get_cur_tick();
+    let mut total = 0;
+    for v in game.bottree.vecs.iter() {
+        assert!(v.windows(2).all(|w| w[0] < w[1]));
+        total += v.len();
+    }
+    assert_eq!(total, tk.bots.len());
+    for b in tk.bots.iter() {
+        let g = (b.x.tile() >> GROUPSHIFT) + ((b.y.tile() >> GROUPSHIFT) * game.bottree.groupw);
+        assert!(game.bottree.vecs[g as usize].contains(&b.id));
+    }
+}
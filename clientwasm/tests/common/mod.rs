@@ -0,0 +1,57 @@
+// helpers shared by the integration tests, pulled in with `mod common;`
+// each test file only uses some of them
+#![allow(dead_code)]
+
+use clientwasm::sim::steer::Command;
+use clientwasm::sim::{Game, GameMap, MapTiles, TickResult, Fx};
+use stratapaint_protocol::{NetStep, PaintDelta, PaintUpdate};
+
+pub fn fx(v: f32) -> Fx {
+    Fx::from_bits((v * 65536.0) as i32)
+}
+
+// a command's color at full strength
+pub fn full(cmd: Command) -> u32 {
+    u32::from(cmd.to_px(0xff))
+}
+
+// a box all in one color
+pub fn rect(x: u32, y: u32, w: u32, h: u32, color: u32) -> PaintDelta {
+    PaintDelta::from_pixels(x, y, w, h, &vec![Some(color); (w * h) as usize])
+}
+
+pub fn paint(player: u32, x: u32, y: u32, w: u32, h: u32, color: u32) -> PaintUpdate {
+    PaintUpdate{player, delta: rect(x, y, w, h, color)}
+}
+
+// the same box of a command at full strength from each of the first few players
+pub fn paint_all(players: u32, x: u32, y: u32, w: u32, h: u32, cmd: Command) -> Vec<PaintUpdate> {
+    (0..players).map(|player| paint(player, x, y, w, h, full(cmd))).collect()
+}
+
+pub fn run_netstep(game: &mut Game, n: u32, updates: Vec<PaintUpdate>) {
+    run_netstep_checked(game, n, updates, |_| ());
+}
+
+// run a netstep, checking the game after every tick
+pub fn run_netstep_checked<F: FnMut(&Game)>(game: &mut Game, n: u32, updates: Vec<PaintUpdate>, mut check: F) {
+    game.add_netstep(NetStep{n, updates});
+    while !matches!(game.tick(), TickResult::Waiting) {
+        check(game);
+    }
+    game.prune(game.curtick);
+}
+
+pub fn fill(map: &mut GameMap, x: u32, y: u32, w: u32, h: u32, tile: MapTiles) {
+    for ty in y..(y + h) {
+        for tx in x..(x + w) {
+            map.set_tile(tx, ty, tile);
+        }
+    }
+}
+
+pub fn walls(map: &mut GameMap, tiles: &[(u32, u32)]) {
+    for (x, y) in tiles.iter() {
+        map.set_tile(*x, *y, MapTiles::Wall);
+    }
+}
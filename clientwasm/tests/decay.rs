@@ -0,0 +1,88 @@
+// paint fades over time unless it gets painted again, the same way everywhere
+use clientwasm::sim::steer::Command;
+use clientwasm::sim::{Game, GameMap, TickResult, Fx, Px};
+use stratapaint_protocol::NetStep;
+
+mod common;
+use common::{full, paint};
+
+fn strength(layer: &GameMap, x: u32, y: u32) -> u8 {
+    Command::from_px(layer.get(x, y)).map_or(0, |(_, s)| s)
+}
+
+#[test]
+fn fading_adds_up() {
+    let mut game = Game::empty(50, 50, 1, 4, Fx::from_ratio(1, 10), 1);
+    game.decay = 30;
+
+    // a bit each tick, and over a game second it is what it should be
+    let lost: u32 = (1..=10).map(|t| game.fade_at(t) as u32).sum();
+    assert_eq!(lost, (30 * Fx::from_ratio(1, 10).to_bits() as u32 * 10) >> 16);
+    assert!((1..=10).all(|t| (2..=3).contains(&game.fade_at(t))));
+
+    game.decay = 0;
+    assert!((1..100).all(|t| game.fade_at(t) == 0));
+}
+
+#[test]
+fn paint_fades_away() {
+    let mut game = Game::empty(50, 50, 1, 4, Fx::from_ratio(1, 10), 1);
+    game.decay = 25;
+    let updates = vec![
+        paint(0, 0, 0, 10, 10, full(Command::Hold)),
+        paint(0, 20, 20, 5, 5, u32::from(Command::Attract.to_px(0x20))),
+    ];
+    game.add_netstep(NetStep{n: 0, updates});
+
+    // weaker paint runs out first, and both get weaker the same
+    let mut layers = vec![game.get_cur_tick().paints.clone()];
+    for n in 1..20 {
+        game.add_netstep(NetStep{n, updates: Vec::new()});
+        while !matches!(game.tick(), TickResult::Waiting) {
+            let tk = game.get_cur_tick();
+            // it went down on the first tick, after that tick's fade
+            let lost: u32 = (2..=tk.tick).map(|t| game.fade_at(t) as u32).sum();
+            assert_eq!(strength(&tk.paints[0], 5, 5) as u32, 0xff - lost.min(0xff));
+            assert_eq!(strength(&tk.paints[0], 22, 22) as u32, 0x20 - lost.min(0x20));
+            layers.push(tk.paints.clone());
+        }
+    }
+    let tk = game.get_cur_tick();
+    assert!(tk.paints[0].get(22, 22) == Px::CLEAR);
+    assert!(strength(&tk.paints[0], 5, 5) > 0);
+
+    // and history can still step back through it
+    for (t, want) in layers.iter().enumerate() {
+        let got = game.paints_at(t as u32).unwrap();
+        assert!(got[0].data == want[0].data, "paint at tick {} came back wrong", t);
+    }
+
+    // painting again brings it back to full
+    let n = game.curtick / 4;
+    game.add_netstep(NetStep{n, updates: vec![paint(0, 0, 0, 10, 10, full(Command::Hold))]});
+    game.tick();
+    assert_eq!(strength(&game.get_cur_tick().paints[0], 5, 5), 0xff);
+}
+
+#[test]
+fn faded_paint_pulls_less() {
+    // the same bot on the same paint, once fresh and once mostly faded
+    let mut speeds = Vec::new();
+    for start in [0xff, 0x40].iter() {
+        let mut game = Game::empty(100, 100, 1, 4, Fx::from_ratio(1, 10), 1);
+        game.decay = 0;
+        let id = game.spawn_bot(Fx::from_ratio(101, 2), Fx::from_ratio(101, 2), 0);
+        game.add_netstep(NetStep{n: 0, updates: vec![paint(0, 0, 0, 100, 100, u32::from(Command::Dir(0).to_px(*start)))]});
+        game.tick();
+        speeds.push(game.get_cur_tick().bots.get(id).unwrap().vx);
+    }
+    assert!(speeds[1] < speeds[0]);
+}
+
+#[test]
+fn decay_comes_along_in_snapshots() {
+    let mut game = Game::empty(50, 50, 1, 4, Fx::from_ratio(1, 10), 1);
+    game.decay = 17;
+    let restored = Game::from_snapshot(&game.snapshot()).unwrap();
+    assert_eq!(restored.decay, 17);
+}
@@ -0,0 +1,58 @@
+// lockstep only works if every client computes exactly the same ticks
+// the same run has to come out bit for bit the same natively and in wasm
+// run the wasm side with `wasm-pack test --node clientwasm`
+use clientwasm::sim::{fixed, Game, Fx, StateHash};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen_test::wasm_bindgen_test;
+
+mod common;
+use common::{paint, run_netstep};
+
+// boil a snapshot down to something to compare against
+fn hash(b: &[u8]) -> u64 {
+    let mut h = StateHash::new();
+    h.bytes(b);
+    h.finish()
+}
+
+fn run(seed: u32, ticks: u32) -> Game {
+    let mut game = Game::new(200, 200, 2, 4, Fx::from_ratio(100, 1000), seed);
+    let mut n: u32 = 0;
+    while game.curtick < ticks {
+        let updates = if n.is_multiple_of(5) { vec![paint(0, n % 190, (n * 7) % 190, 4, 4, 0xff0000ff)] } else { Vec::new() };
+        run_netstep(&mut game, n, updates);
+        n += 1;
+    }
+    game
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn same_ticks_everywhere() {
+    let a = run(7, 200).snapshot();
+    let b = run(7, 200).snapshot();
+    assert!(a == b, "two runs of the same game came out different");
+
+    let a = run(7, 2000).snapshot();
+
+    // if this changes on purpose (the sim changed), update it from a native run
+    // if it only fails on one platform, something in the sim isn't deterministic
+    assert_eq!(hash(&a), 0x81e6d9f354cbaf15);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn trig_table() {
+    assert_eq!(fixed::sin(0), Fx::ZERO);
+    assert_eq!(fixed::sin(fixed::ANGLES / 4), Fx::ONE);
+    assert_eq!(fixed::cos(fixed::ANGLES / 2), -Fx::ONE);
+    assert_eq!(fixed::sin(fixed::ANGLES * 3 / 4), -Fx::ONE);
+
+    for a in 0..fixed::ANGLES {
+        let s = fixed::sin(a);
+        let c = fixed::cos(a);
+        let err = (s * s + c * c - Fx::ONE).abs();
+        assert!(err <= Fx::from_bits(4), "sin^2 + cos^2 off by {:?} at {}", err, a);
+    }
+}
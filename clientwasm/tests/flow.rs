@@ -0,0 +1,128 @@
+// bots find their way around walls to attract paint, and the flow field keeps up with the paint
+use clientwasm::sim::steer::Command;
+use clientwasm::sim::{FlowField, Game, GameMap, MapTiles, TickResult, Fx, FLOWRAD, UNREACHED};
+use stratapaint_protocol::NetStep;
+
+mod common;
+use common::{full, fx, paint};
+
+fn attract(s: u8) -> u32 {
+    u32::from(Command::Attract.to_px(s))
+}
+
+// a wall across the middle, with a gap at the right end
+//  ..........
+//  ........A.
+//  #########.
+//  ..........
+fn walled() -> (GameMap, GameMap) {
+    let mut map = GameMap::new(10, 10);
+    for x in 0..9 {
+        map.set_tile(x, 5, MapTiles::Wall);
+    }
+    let mut layer = GameMap::new(10, 10);
+    layer.set(8, 2, Command::Attract.to_px(0xff));
+    (map, layer)
+}
+
+fn same(a: &FlowField, b: &FlowField) -> bool {
+    (0..a.h).all(|y| (0..a.w).all(|x| a.dist(x, y) == b.dist(x, y) && a.strength(x, y) == b.strength(x, y)))
+}
+
+#[test]
+fn distance_goes_around_walls() {
+    let (map, layer) = walled();
+    let mut f = FlowField::new(10, 10);
+    f.update(&map, &layer);
+
+    assert_eq!(f.dist(8, 2), 0);
+    assert_eq!(f.dist(0, 2), 8);
+    assert_eq!(f.dist(3, 5), UNREACHED);
+
+    // straight under the paint it is the long way, out through the gap
+    assert_eq!(f.dist(8, 6), 6);
+    assert_eq!(f.dist(0, 6), 14);
+    assert_eq!(f.strength(0, 6), 0xff);
+
+    // so downhill under the wall is toward the gap, not up into the wall
+    let (gx, gy, _) = f.downhill(3, 6).unwrap();
+    assert!(gx > 0 && gy == 0);
+    let (gx, gy, _) = f.downhill(9, 7).unwrap();
+    assert!(gx == 0 && gy < 0);
+    assert_eq!(f.downhill(8, 2), None);
+}
+
+#[test]
+fn walled_off_and_out_of_range() {
+    let mut map = GameMap::new(100, 10);
+    for y in 0..10 {
+        map.set_tile(5, y, MapTiles::Wall);
+    }
+    let mut layer = GameMap::new(100, 10);
+    layer.set(2, 2, Command::Attract.to_px(0xff));
+    let mut f = FlowField::new(100, 10);
+    f.update(&map, &layer);
+    assert_eq!(f.dist(6, 2), UNREACHED);
+    assert_eq!(f.downhill(6, 2), None);
+
+    // and paint only reaches so far
+    layer.set(2, 2, Command::Repel.to_px(0xff));
+    layer.set(6, 0, Command::Attract.to_px(0xff));
+    f.invalidate();
+    f.update(&map, &layer);
+    assert_eq!(f.dist(6 + FLOWRAD, 0), FLOWRAD);
+    assert_eq!(f.dist(7 + FLOWRAD, 0), UNREACHED);
+}
+
+#[test]
+fn keeps_up_with_paint() {
+    let mut game = Game::empty(60, 60, 1, 4, Fx::from_ratio(1, 10), 4);
+    game.decay = 60;
+    for y in 10..50 {
+        game.map.set_tile(30, y, MapTiles::Wall);
+    }
+    let steps = vec![
+        vec![paint(0, 5, 5, 4, 4, attract(0xff))],
+        vec![paint(0, 50, 50, 3, 3, attract(0x80)), paint(0, 40, 10, 2, 2, attract(0x30))],
+        vec![paint(0, 5, 5, 2, 2, full(Command::Hold))],
+        Vec::new(),
+        vec![paint(0, 40, 10, 2, 2, attract(0xff)), paint(0, 20, 30, 10, 1, attract(0x10))],
+        vec![paint(0, 50, 50, 3, 3, 0)],
+        Vec::new(),
+        Vec::new(),
+    ];
+
+    // after every tick it is what working it out from scratch gets
+    for (n, updates) in steps.into_iter().enumerate() {
+        game.add_netstep(NetStep{n: n as u32, updates});
+        while !matches!(game.tick(), TickResult::Waiting) {
+            let mut fresh = FlowField::new(60, 60);
+            fresh.update(&game.map, &game.get_cur_tick().paints[0]);
+            assert!(same(&game.flows[0], &fresh), "flow field off at tick {}", game.curtick);
+        }
+    }
+}
+
+#[test]
+fn bots_find_the_gap() {
+    // the paint is on the other side of a wall, too far to pull straight at it
+    let mut game = Game::empty(40, 40, 1, 4, Fx::from_ratio(1, 10), 6);
+    game.decay = 0;
+    for x in 0..34 {
+        game.map.set_tile(x, 20, MapTiles::Wall);
+    }
+    let ids: Vec<u32> = (0..5).map(|i| game.spawn_bot(fx(5.5 + i as f32 * 2.0), fx(30.5), 0)).collect();
+
+    let mut updates = vec![paint(0, 3, 5, 6, 6, attract(0xff))];
+    for n in 0..150 {
+        game.add_netstep(NetStep{n, updates});
+        while !matches!(game.tick(), TickResult::Waiting) {}
+        updates = Vec::new();
+    }
+
+    let tk = game.get_cur_tick();
+    for id in ids.iter() {
+        let b = tk.bots.get(*id).unwrap();
+        assert!(b.y < Fx::from_int(20), "bot {} never got around the wall", id);
+    }
+}
@@ -0,0 +1,139 @@
+// neighbor queries on the location groups
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use clientwasm::sim::{Bots, BotState, LocationGroups, Fx};
+
+mod common;
+use common::fx;
+
+struct World {
+    groups: LocationGroups,
+    bots: Bots,
+}
+
+impl World {
+    fn new() -> World {
+        World {
+            groups: LocationGroups::new(100, 100),
+            bots: Bots::new(),
+        }
+    }
+
+    fn add(&mut self, id: u32, x: Fx, y: Fx, team: i32) {
+        self.bots.insert(BotState {
+            id,
+            health: Fx::from_int(100),
+            x,
+            y,
+            vx: Fx::ZERO,
+            vy: Fx::ZERO,
+            team,
+            cur_target: 0,
+        });
+        self.groups.add_bot(id, x.tile(), y.tile());
+    }
+}
+
+#[test]
+fn square_crosses_groups() {
+    let mut w = World::new();
+    // added out of order, and spread over the group edges at 8 and 16
+    w.add(5, fx(15.5), fx(10.0), 0);
+    w.add(2, fx(7.5), fx(10.0), 0);
+    w.add(9, fx(10.0), fx(13.0), 1);
+    w.add(1, fx(10.0), fx(10.0), 1);
+    w.add(7, fx(16.5), fx(10.0), 0);
+    w.add(3, fx(10.0), fx(4.0), 0);
+
+    // the edge of the range counts
+    assert_eq!(w.groups.in_square(&w.bots, fx(10.0), fx(10.0), fx(5.5)), vec![1, 2, 5, 9]);
+    assert_eq!(w.groups.in_square(&w.bots, fx(10.0), fx(10.0), fx(6.0)), vec![1, 2, 3, 5, 9]);
+    assert_eq!(w.groups.in_square(&w.bots, fx(10.0), fx(10.0), Fx::ZERO), vec![1]);
+    assert!(w.groups.in_square(&w.bots, fx(50.0), fx(50.0), fx(10.0)).is_empty());
+}
+
+#[test]
+fn rect_at_the_edges() {
+    let mut w = World::new();
+    w.add(1, fx(0.5), fx(0.5), 0);
+    w.add(2, fx(99.5), fx(99.5), 0);
+    w.add(3, fx(99.5), fx(0.5), 0);
+
+    // boxes hanging off the map just get clipped
+    assert_eq!(w.groups.in_rect(&w.bots, fx(-10.0), fx(-10.0), fx(1.0), fx(1.0)), vec![1]);
+    assert_eq!(w.groups.in_rect(&w.bots, fx(90.0), fx(-10.0), fx(500.0), fx(500.0)), vec![2, 3]);
+    assert_eq!(w.groups.in_rect(&w.bots, fx(-10.0), fx(-10.0), fx(500.0), fx(500.0)), vec![1, 2, 3]);
+    assert!(w.groups.in_rect(&w.bots, fx(-10.0), fx(-10.0), fx(-1.0), fx(-1.0)).is_empty());
+    assert!(w.groups.in_rect(&w.bots, fx(200.0), fx(200.0), fx(300.0), fx(300.0)).is_empty());
+    // upside down boxes are empty
+    assert!(w.groups.in_rect(&w.bots, fx(1.0), fx(1.0), fx(0.0), fx(0.0)).is_empty());
+}
+
+#[test]
+fn nearest_with_a_filter() {
+    let mut w = World::new();
+    w.add(1, fx(50.0), fx(50.0), 0);
+    w.add(2, fx(51.0), fx(50.0), 0);
+    w.add(3, fx(50.0), fx(52.0), 1);
+    w.add(4, fx(48.0), fx(48.0), 1); // same distance as 3
+    w.add(5, fx(53.0), fx(50.0), 1);
+    w.add(6, fx(60.0), fx(50.0), 1);
+
+    let all = |_: &BotState| true;
+    assert_eq!(w.groups.nearest(&w.bots, fx(50.0), fx(50.0), fx(5.0), 10, all), vec![1, 2, 3, 4, 5]);
+    assert_eq!(w.groups.nearest(&w.bots, fx(50.0), fx(50.0), fx(5.0), 2, all), vec![1, 2]);
+
+    // ties go to the lower id
+    let enemy = |b: &BotState| b.team != 0;
+    assert_eq!(w.groups.nearest(&w.bots, fx(50.0), fx(50.0), fx(5.0), 1, enemy), vec![3]);
+    assert_eq!(w.groups.nearest(&w.bots, fx(50.0), fx(50.0), fx(5.0), 10, enemy), vec![3, 4, 5]);
+    assert_eq!(w.groups.nearest(&w.bots, fx(50.0), fx(50.0), fx(20.0), 10, enemy), vec![3, 4, 5, 6]);
+
+    // leaving out the one we are asking for
+    let others = |b: &BotState| b.id != 1;
+    assert_eq!(w.groups.nearest(&w.bots, fx(50.0), fx(50.0), fx(1.0), 1, others), vec![2]);
+    assert!(w.groups.nearest(&w.bots, fx(50.0), fx(50.0), fx(5.0), 0, all).is_empty());
+}
+
+#[test]
+fn same_as_checking_everything() {
+    let mut w = World::new();
+    let mut rng = XorShiftRng::seed_from_u64(3);
+    for id in 1..500 {
+        let x = Fx::from_bits(rng.gen_range(0, Fx::from_int(100).to_bits()));
+        let y = Fx::from_bits(rng.gen_range(0, Fx::from_int(100).to_bits()));
+        w.add(id, x, y, rng.gen_range(0, 3));
+    }
+
+    for _ in 0..50 {
+        let x = Fx::from_bits(rng.gen_range(Fx::from_int(-10).to_bits(), Fx::from_int(110).to_bits()));
+        let y = Fx::from_bits(rng.gen_range(Fx::from_int(-10).to_bits(), Fx::from_int(110).to_bits()));
+        let r = Fx::from_bits(rng.gen_range(0, Fx::from_int(20).to_bits()));
+        let dist = |b: &BotState| (b.x - x).abs().max((b.y - y).abs());
+
+        let want: Vec<u32> = w.bots.iter().filter(|b| dist(b) <= r).map(|b| b.id).collect();
+        assert_eq!(w.groups.in_square(&w.bots, x, y, r), want);
+
+        let mut want: Vec<(Fx, u32)> = w.bots.iter().filter(|b| b.team == 2 && dist(b) <= r).map(|b| (dist(&b), b.id)).collect();
+        want.sort();
+        let want: Vec<u32> = want.into_iter().take(5).map(|(_, id)| id).collect();
+        assert_eq!(w.groups.nearest(&w.bots, x, y, r, 5, |b| b.team == 2), want);
+    }
+}
+
+#[test]
+fn ranges_are_squares() {
+    let mut w = World::new();
+    w.add(1, fx(50.0), fx(50.0), 0);
+    // out on the diagonal, further than 4 away as the crow flies, but not by the P-Inf norm
+    w.add(2, fx(53.5), fx(53.5), 1);
+    // and just off the side of the square
+    w.add(3, fx(54.25), fx(50.0), 1);
+
+    assert_eq!(w.groups.in_square(&w.bots, fx(50.0), fx(50.0), fx(4.0)), vec![1, 2]);
+    let enemy = |b: &BotState| b.team != 0;
+    assert_eq!(w.groups.nearest(&w.bots, fx(50.0), fx(50.0), fx(4.0), 10, enemy), vec![2]);
+
+    // the diagonal one is closer by the P-Inf norm, even though it is further in a straight line
+    assert_eq!(w.groups.nearest(&w.bots, fx(50.0), fx(50.0), fx(5.0), 10, enemy), vec![2, 3]);
+}
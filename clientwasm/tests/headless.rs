@@ -0,0 +1,90 @@
+// the simulation has to run without a browser, for the server and for tests
+use clientwasm::sim::{Game, TickResult, Fx};
+use stratapaint_protocol::{NetStep, PaintDelta, PaintUpdate};
+
+#[test]
+fn ticks_without_a_canvas() {
+    let mut game = Game::new(200, 200, 1, 4, Fx::from_ratio(1, 10), 7);
+
+    // nothing happens until the first netstep shows up
+    assert!(matches!(game.tick(), TickResult::Waiting));
+    assert_eq!(game.curtick, 0);
+
+    for n in 0..10 {
+        game.add_netstep(NetStep{n, updates: Vec::new()});
+        assert!(matches!(game.tick(), TickResult::NetStep(m) if m == n));
+        for _ in 1..4 {
+            assert!(matches!(game.tick(), TickResult::Ticked));
+        }
+        assert!(matches!(game.tick(), TickResult::Waiting));
+        game.prune(game.curtick);
+    }
+
+    assert_eq!(game.curtick, 40);
+    let tk = game.get_cur_tick();
+    assert_eq!(tk.tick, 40);
+    assert_eq!(tk.bots.len() as u32, tk.teambotcount.iter().sum::<u32>());
+}
+
+#[test]
+fn snapshot_round_trip() {
+    let mut game = Game::new(200, 200, 1, 4, Fx::from_ratio(1, 10), 7);
+    for n in 0..3 {
+        game.add_netstep(NetStep{n, updates: Vec::new()});
+        while !matches!(game.tick(), TickResult::Waiting) {}
+    }
+
+    let state = game.snapshot();
+    let mut copy = Game::from_snapshot(&state).unwrap();
+    assert_eq!(copy.curtick, game.curtick);
+    assert_eq!(copy.snapshot(), state);
+    assert_eq!(copy.get_cur_tick().hash(), game.get_cur_tick().hash());
+
+    // picks up from the next netstep, and stays in step with the original
+    for n in 3..10 {
+        for g in [&mut game, &mut copy].iter_mut() {
+            g.add_netstep(NetStep{n, updates: Vec::new()});
+            assert!(matches!(g.tick(), TickResult::NetStep(m) if m == n));
+            while !matches!(g.tick(), TickResult::Waiting) {}
+        }
+    }
+    assert_eq!(copy.snapshot(), game.snapshot());
+
+    assert!(Game::from_snapshot(&state[..state.len() - 1]).is_err());
+    assert!(Game::from_snapshot(&[]).is_err());
+}
+
+#[test]
+fn hash_covers_paint() {
+    let mut a = Game::new(200, 200, 1, 4, Fx::from_ratio(1, 10), 7);
+    let mut b = Game::new(200, 200, 1, 4, Fx::from_ratio(1, 10), 7);
+    assert_eq!(a.get_cur_tick().hash(), b.get_cur_tick().hash());
+
+    let delta = PaintDelta::from_pixels(10, 10, 2, 1, &[Some(0xff0000ff), Some(0xff0000ff)]);
+    a.add_netstep(NetStep{n: 0, updates: vec![PaintUpdate{player: 0, delta}]});
+    b.add_netstep(NetStep{n: 0, updates: Vec::new()});
+    assert!(matches!(a.tick(), TickResult::NetStep(0)));
+    assert!(matches!(b.tick(), TickResult::NetStep(0)));
+
+    // same bots, different paint
+    assert_ne!(a.get_cur_tick().hash(), b.get_cur_tick().hash());
+}
+
+#[test]
+fn teams_start_apart() {
+    let game = Game::new(400, 400, 3, 4, Fx::from_ratio(1, 10), 7);
+    let tk = game.get_cur_tick();
+    assert_eq!(tk.paints.len(), 3);
+    assert_eq!(tk.teambotcount.len(), 3);
+    assert!(tk.teambotcount.iter().all(|c| *c == tk.teambotcount[0] && *c > 0));
+
+    // every bot is closer to its own team's start than any other team's
+    for b in tk.bots.iter() {
+        let dist = |t: u32| {
+            let (x, y) = game.team_start(t, 3);
+            (x - b.x).abs().max((y - b.y).abs())
+        };
+        let own = dist(b.team as u32);
+        assert!((0..3).all(|t| t == b.team as u32 || dist(t) > own));
+    }
+}
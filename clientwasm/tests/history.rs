@@ -0,0 +1,66 @@
+// older ticks only keep what their paint changed, and the layers can be stepped back from that
+use clientwasm::sim::steer::Command;
+use clientwasm::sim::{Game, GameMap, TickResult, Fx};
+use stratapaint_protocol::NetStep;
+
+mod common;
+use common::{full, paint};
+
+fn same(a: &[GameMap], b: &[GameMap]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.data == b.data)
+}
+
+#[test]
+fn step_paint_back() {
+    let mut game = Game::new(200, 200, 2, 4, Fx::from_ratio(1, 10), 3);
+
+    // overlapping strokes from both teams, so undoing has to go in the right order
+    let updates = vec![
+        vec![paint(0, 10, 10, 20, 20, full(Command::Attract))],
+        vec![paint(0, 15, 15, 20, 20, full(Command::Repel)), paint(1, 10, 10, 5, 5, full(Command::Hold))],
+        Vec::new(),
+        vec![paint(0, 12, 12, 4, 4, full(Command::Dir(7))), paint(0, 12, 12, 2, 2, full(Command::Hold))],
+    ];
+
+    let mut layers = vec![game.get_cur_tick().paints.clone()];
+    for (n, u) in updates.into_iter().enumerate() {
+        game.add_netstep(NetStep{n: n as u32, updates: u});
+        while !matches!(game.tick(), TickResult::Waiting) {
+            layers.push(game.get_cur_tick().paints.clone());
+        }
+    }
+    assert_eq!(game.curtick as usize, layers.len() - 1);
+
+    for (t, want) in layers.iter().enumerate() {
+        let got = game.paints_at(t as u32).unwrap();
+        assert!(same(&got, want), "paint at tick {} came back wrong", t);
+    }
+    assert!(game.paints_at(game.curtick + 1).is_none());
+}
+
+#[test]
+fn history_only_keeps_changes() {
+    let mut game = Game::new(200, 200, 2, 4, Fx::from_ratio(1, 10), 3);
+    game.add_netstep(NetStep{n: 0, updates: vec![paint(1, 50, 50, 3, 2, full(Command::Attract))]});
+    game.add_netstep(NetStep{n: 1, updates: Vec::new()});
+    while !matches!(game.tick(), TickResult::Waiting) {}
+
+    // full layers on the current tick only
+    let cur = game.curtick;
+    for tk in game.states.iter() {
+        assert_eq!(tk.paints.is_empty(), tk.tick != cur);
+    }
+
+    // the one update, covering just its own area
+    let painted: Vec<_> = game.states.iter().filter(|t| !t.unpaint.is_empty()).collect();
+    assert_eq!(painted.len(), 1);
+    assert_eq!(painted[0].tick, 1);
+    let (team, d) = &painted[0].unpaint[0];
+    assert_eq!(*team, 1);
+    assert_eq!((d.x, d.y, d.w, d.h), (50, 50, 3, 2));
+
+    // can't go back past what was pruned
+    game.prune(4);
+    assert!(game.paints_at(0).is_none());
+    assert!(game.paints_at(4).is_some());
+}
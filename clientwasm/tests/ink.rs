@@ -0,0 +1,80 @@
+// paint costs ink, paint a team can't pay for gets dropped, and ink comes back over time
+use clientwasm::sim::steer::Command;
+use clientwasm::sim::{paint_cost, Game, Fx, Px, MAXINK, STARTINK};
+use stratapaint_protocol::PaintUpdate;
+
+mod common;
+use common::{full, fx, rect, run_netstep};
+
+// ink back per tick, the same way the sim works it out
+fn regen(game: &Game, bases: u64) -> u32 {
+    (((200 + 300 * bases) * 0xff * game.tickstep.to_bits() as u64) >> 16) as u32
+}
+
+#[test]
+fn costs_by_kind_and_strength() {
+    let cost = |c: Command| paint_cost(&rect(0, 0, 10, 10, full(c)));
+    assert_eq!(cost(Command::Attract), 100 * 0xff);
+    assert_eq!(cost(Command::Repel), 100 * 0xff);
+    assert_eq!(cost(Command::Dir(17)), 100 * 0xff);
+    assert_eq!(cost(Command::Hold), 2 * 100 * 0xff);
+
+    // weaker paint is cheaper, and erasing is free
+    assert_eq!(paint_cost(&rect(0, 0, 10, 10, u32::from(Command::Attract.to_px(0x40)))), 100 * 0x40);
+    assert_eq!(paint_cost(&rect(0, 0, 10, 10, u32::from(Px::CLEAR))), 0);
+}
+
+#[test]
+fn too_much_paint_is_dropped() {
+    let mut game = Game::empty(200, 200, 2, 4, Fx::from_ratio(1, 10), 1);
+    game.decay = 0;
+    let hold = full(Command::Hold);
+    let big = rect(0, 0, 150, 150, hold);
+    assert!(paint_cost(&big) > STARTINK as u64);
+
+    run_netstep(&mut game, 0, vec![PaintUpdate{player: 0, delta: big}]);
+    let tk = game.get_cur_tick();
+    assert!(tk.paints[0].get(10, 10) == Px::CLEAR);
+    assert_eq!(tk.ink[0], STARTINK + 4 * regen(&game, 0));
+
+    // something smaller goes through, and gets paid for
+    let before = tk.ink[0];
+    let small = rect(0, 0, 10, 10, hold);
+    let cost = paint_cost(&small) as u32;
+    run_netstep(&mut game, 1, vec![PaintUpdate{player: 0, delta: small}]);
+    let tk = game.get_cur_tick();
+    assert!(tk.paints[0].get(5, 5) == Command::Hold.to_px(0xff));
+    assert_eq!(tk.ink[0], before - cost + 4 * regen(&game, 0));
+    assert_eq!(tk.ink[1], before + 4 * regen(&game, 0));
+}
+
+#[test]
+fn bases_bring_ink_back_up_to_the_max() {
+    let mut game = Game::empty(100, 100, 2, 4, Fx::from_ratio(1, 10), 1);
+    for i in 0..4 {
+        game.spawn_base(fx(10.5 + 20.0 * i as f32), fx(10.5), 0);
+    }
+    game.spawn_base(fx(50.5), fx(90.5), 1);
+
+    run_netstep(&mut game, 0, Vec::new());
+    let tk = game.get_cur_tick();
+    assert_eq!(tk.ink[0], STARTINK + 4 * regen(&game, 4));
+    assert_eq!(tk.ink[1], STARTINK + 4 * regen(&game, 1));
+    assert_eq!(game.ink_left(0), Fx::from_bits((((tk.ink[0] as i64) << 16) / MAXINK as i64) as i32));
+
+    // but never past it
+    let mut n = 1;
+    while game.get_cur_tick().ink[0] < MAXINK {
+        run_netstep(&mut game, n, Vec::new());
+        n += 1;
+        assert!(n < 200, "ink never filled up");
+    }
+    run_netstep(&mut game, n, Vec::new());
+    assert_eq!(game.get_cur_tick().ink[0], MAXINK);
+    assert_eq!(game.ink_left(0), Fx::from_int(1));
+    assert!(game.get_cur_tick().ink[1] < MAXINK);
+
+    // and it comes along in snapshots
+    let restored = Game::from_snapshot(&game.snapshot()).unwrap();
+    assert_eq!(restored.get_cur_tick().ink, game.get_cur_tick().ink);
+}
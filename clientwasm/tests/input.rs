@@ -0,0 +1,123 @@
+// our own paint shows up on the overlay until the server sends it back
+use clientwasm::brush::{Brush, ERASE};
+use clientwasm::input::{Input, ERASING};
+use clientwasm::sim::steer::Command;
+use clientwasm::sim::{Game, GameMap, Px, TickResult, Fx};
+use stratapaint_protocol::{NetStep, PaintDelta, PaintUpdate};
+
+fn dot(x: u32, y: u32, cmd: Command) -> PaintDelta {
+    Brush::Circle{x, y, rad: 2}.paint(&GameMap::new(50, 50), u32::from(cmd.to_px(0xff))).unwrap()
+}
+
+#[test]
+fn overlay_until_it_lands() {
+    let mut input = Input::new(50, 50);
+    let a = dot(10, 10, Command::Attract);
+    let b = dot(30, 30, Command::Repel);
+    input.stroke(a.clone());
+    input.stroke(b.clone());
+    assert_eq!(input.pending(), 2);
+    assert!(input.overlay.get(10, 10) == Command::Attract.to_px(0xff));
+    assert!(input.overlay.get(30, 30) == Command::Repel.to_px(0xff));
+
+    // someone else's paint doesn't count as ours
+    input.landed(&NetStep{n: 0, updates: vec![PaintUpdate{player: 1, delta: a.clone()}]}, 3);
+    assert_eq!(input.pending(), 2);
+
+    input.landed(&NetStep{n: 1, updates: vec![PaintUpdate{player: 3, delta: a}]}, 3);
+    assert_eq!(input.pending(), 1);
+    assert!(input.overlay.get(10, 10) == Px::CLEAR);
+    assert!(input.overlay.get(30, 30) == Command::Repel.to_px(0xff));
+
+    input.landed(&NetStep{n: 2, updates: vec![PaintUpdate{player: 3, delta: b}]}, 3);
+    assert_eq!(input.pending(), 0);
+    assert!(input.overlay.data.iter().all(|p| *p == Px::CLEAR));
+}
+
+#[test]
+fn dropped_strokes_expire() {
+    let mut input = Input::new(50, 50);
+    input.landed(&NetStep{n: 4, updates: Vec::new()}, 0);
+    input.stroke(dot(10, 10, Command::Hold));
+
+    // the server never sent it, so it gets given up on eventually
+    for n in 5..20 {
+        input.landed(&NetStep{n, updates: Vec::new()}, 0);
+        assert_eq!(input.pending(), 1);
+    }
+    input.landed(&NetStep{n: 21, updates: Vec::new()}, 0);
+    assert_eq!(input.pending(), 0);
+    assert!(input.overlay.get(10, 10) == Px::CLEAR);
+}
+
+#[test]
+fn dropped_strokes_dont_hold_up_the_rest() {
+    let mut input = Input::new(50, 50);
+    let a = dot(10, 10, Command::Attract);
+    let b = dot(30, 30, Command::Repel);
+    let c = dot(10, 40, Command::Hold);
+    input.stroke(a.clone());
+    input.stroke(b);
+    input.stroke(c.clone());
+
+    // the server dropped a and b, so c coming back takes them off too, not just a
+    input.landed(&NetStep{n: 0, updates: vec![PaintUpdate{player: 3, delta: c}]}, 3);
+    assert_eq!(input.pending(), 0);
+    assert!(input.overlay.data.iter().all(|p| *p == Px::CLEAR));
+
+    // and one that never went out can't take anything off
+    input.stroke(a);
+    input.landed(&NetStep{n: 1, updates: vec![PaintUpdate{player: 3, delta: dot(20, 20, Command::Hold)}]}, 3);
+    assert_eq!(input.pending(), 1);
+
+    // an error means something got turned down
+    input.clear();
+    assert_eq!(input.pending(), 0);
+    assert!(input.overlay.get(10, 10) == Px::CLEAR);
+}
+
+#[test]
+fn resync_starts_over() {
+    let mut server = Game::new(50, 50, 1, 4, Fx::from_ratio(1, 10), 2);
+    server.decay = 0;
+    let mut input = Input::new(50, 50);
+    let a = dot(10, 10, Command::Attract);
+    input.stroke(a.clone());
+    input.stroke(dot(30, 30, Command::Repel));
+
+    // a made it in on the server, but we went wrong before seeing it, and the other one got dropped
+    for n in 0..6 {
+        let updates = if n == 2 { vec![PaintUpdate{player: 0, delta: a.clone()}] } else { Vec::new() };
+        if n < 2 {
+            input.landed(&NetStep{n, updates: updates.clone()}, 0);
+        }
+        server.add_netstep(NetStep{n, updates});
+        while !matches!(server.tick(), TickResult::Waiting) {}
+    }
+    let game = Game::from_snapshot(&server.snapshot()).unwrap();
+    input.clear();
+    assert_eq!(input.pending(), 0);
+    assert!(input.overlay.data.iter().all(|p| *p == Px::CLEAR));
+    assert!(game.get_cur_tick().paints[0].get(10, 10) == Command::Attract.to_px(0xff));
+
+    // strokes after it still wait their turn, the netsteps didn't start over
+    for n in 2..6 {
+        input.landed(&NetStep{n, updates: Vec::new()}, 0);
+    }
+    input.stroke(dot(20, 20, Command::Hold));
+    input.landed(&NetStep{n: 6, updates: Vec::new()}, 0);
+    input.landed(&NetStep{n: 7, updates: Vec::new()}, 0);
+    assert_eq!(input.pending(), 1);
+}
+
+#[test]
+fn erasing_shows_too() {
+    let mut input = Input::new(50, 50);
+    let rub = Brush::Circle{x: 10, y: 10, rad: 2}.paint(&GameMap::new(50, 50), ERASE).unwrap();
+    input.stroke(rub.clone());
+    assert!(input.overlay.get(10, 10) == ERASING);
+    assert!(input.overlay.get(20, 20) == Px::CLEAR);
+
+    input.landed(&NetStep{n: 0, updates: vec![PaintUpdate{player: 0, delta: rub}]}, 0);
+    assert!(input.overlay.get(10, 10) == Px::CLEAR);
+}
@@ -0,0 +1,132 @@
+// maps loaded from images, and games started on them
+use clientwasm::sim::{Game, MapError, MapFile, MapTiles, Marker, Fx};
+
+const OPEN: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+const WALL: [u8; 4] = [0, 0, 0, 0xff];
+
+fn base(team: u8) -> [u8; 4] {
+    [0xff, 0, team, 0xff]
+}
+
+fn spawn(team: u8) -> [u8; 4] {
+    [0, 0xff, team, 0xff]
+}
+
+// an open map with a wall down the middle column, and whatever else put on top
+fn rgba(w: u32, h: u32, marks: &[(u32, u32, [u8; 4])]) -> Vec<u8> {
+    let mut px = vec![OPEN; (w * h) as usize];
+    for y in 0..h {
+        px[(w / 2 + y * w) as usize] = WALL;
+    }
+    for (x, y, c) in marks.iter() {
+        px[(x + y * w) as usize] = *c;
+    }
+    px.concat()
+}
+
+fn png(w: u32, h: u32, color: png::ColorType, data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    {
+        let mut enc = png::Encoder::new(&mut out, w, h);
+        enc.set_color(color);
+        enc.set_depth(png::BitDepth::Eight);
+        let mut wr = enc.write_header().unwrap();
+        wr.write_image_data(data).unwrap();
+    }
+    out
+}
+
+fn center(team: u32, x: i32, y: i32) -> Marker {
+    Marker{team, x: Fx::from_int(x) + Fx::from_ratio(1, 2), y: Fx::from_int(y) + Fx::from_ratio(1, 2)}
+}
+
+#[test]
+fn reads_the_palette() {
+    let data = rgba(20, 10, &[(2, 3, base(0)), (17, 6, base(1)), (3, 8, spawn(0)), (5, 1, spawn(0)), (0, 0, [9, 9, 9, 0])]);
+    let mf = MapFile::from_rgba(20, 10, &data).unwrap();
+
+    assert_eq!((mf.map.w, mf.map.h), (20, 10));
+    assert!(mf.map.get_tile(10, 4) == MapTiles::Wall);
+    assert!(mf.map.get_tile(9, 4) == MapTiles::Nothing);
+    // markers and see through pixels are open ground
+    assert!(mf.map.get_tile(2, 3) == MapTiles::Nothing);
+    assert!(mf.map.get_tile(0, 0) == MapTiles::Nothing);
+
+    assert_eq!(mf.bases, vec![center(0, 2, 3), center(1, 17, 6)]);
+    assert_eq!(mf.spawns, vec![center(0, 5, 1), center(0, 3, 8)]);
+}
+
+#[test]
+fn png_same_as_rgba() {
+    let data = rgba(16, 12, &[(1, 1, base(0)), (14, 10, base(1)), (2, 9, spawn(1))]);
+    let want = MapFile::from_rgba(16, 12, &data).unwrap();
+
+    let got = MapFile::from_png(&png(16, 12, png::ColorType::Rgba, &data)).unwrap();
+    assert!(got.map.data == want.map.data);
+    assert_eq!(got.bases, want.bases);
+    assert_eq!(got.spawns, want.spawns);
+
+    // without alpha too
+    let rgb: Vec<u8> = data.chunks(4).flat_map(|p| p[..3].to_vec()).collect();
+    let got = MapFile::from_png(&png(16, 12, png::ColorType::Rgb, &rgb)).unwrap();
+    assert!(got.map.data == want.map.data);
+    assert_eq!(got.bases, want.bases);
+
+    // js can hand either one over
+    let got = MapFile::load(16, 12, &png(16, 12, png::ColorType::Rgba, &data)).unwrap();
+    assert!(got.map.data == want.map.data);
+    let got = MapFile::load(16, 12, &data).unwrap();
+    assert!(got.map.data == want.map.data);
+    assert_eq!(got.spawns, want.spawns);
+}
+
+#[test]
+fn bad_maps() {
+    let mut data = rgba(8, 8, &[]);
+    assert_eq!(MapFile::from_rgba(8, 7, &data).err(), Some(MapError::Size(8, 7)));
+    assert_eq!(MapFile::from_rgba(0, 0, &[]).err(), Some(MapError::Size(0, 0)));
+
+    data[(3 + 2 * 8) * 4] = 0x80;
+    assert_eq!(MapFile::from_rgba(8, 8, &data).err(), Some(MapError::BadColor(3, 2, 0x80ffffff)));
+
+    assert!(matches!(MapFile::from_png(b"not a png"), Err(MapError::Png(_))));
+
+    // one base isn't enough for two teams
+    let mf = MapFile::from_rgba(8, 8, &rgba(8, 8, &[(1, 1, base(0))])).unwrap();
+    assert!(mf.check_teams(1).is_ok());
+    assert_eq!(mf.check_teams(2), Err(MapError::NoBase(1)));
+    assert_eq!(Game::with_map(&mf, 2, 4, Fx::from_ratio(1, 10), 3).err(), Some(MapError::NoBase(1)));
+}
+
+#[test]
+fn game_on_a_map() {
+    // team 2 is on the map, but not in the game
+    let data = rgba(200, 100, &[(20, 50, base(0)), (180, 50, base(1)), (150, 20, spawn(1)), (150, 80, spawn(1)), (100, 5, base(2))]);
+    let mf = MapFile::from_rgba(200, 100, &data).unwrap();
+    let game = Game::with_map(&mf, 2, 4, Fx::from_ratio(1, 10), 3).unwrap();
+    assert!(game.map.data == mf.map.data);
+
+    // markers come row by row, so team 2's base is first
+    let tk = game.get_cur_tick();
+    let bases: Vec<_> = tk.bases.iter().map(|b| (b.team, b.x, b.y)).collect();
+    assert_eq!(bases, vec![(0, mf.bases[1].x, mf.bases[1].y), (1, mf.bases[2].x, mf.bases[2].y)]);
+
+    // team 0 starts at its base, team 1 split between its spawn points
+    let near = |x: Fx, y: Fx, m: &Marker| (x - m.x).abs() <= Fx::from_int(12) && (y - m.y).abs() <= Fx::from_int(12);
+    let mut split = [0, 0];
+    for b in tk.bots.iter() {
+        if b.team == 0 {
+            assert!(near(b.x, b.y, &mf.bases[1]));
+        } else {
+            assert_eq!(b.team, 1);
+            let i = mf.spawns.iter().position(|s| near(b.x, b.y, s)).expect("team 1 bot away from its spawns");
+            split[i] += 1;
+        }
+    }
+    assert_eq!(split[0], split[1]);
+    assert_eq!(tk.teambotcount[0], tk.teambotcount[1]);
+
+    // and everyone loading it gets the same game
+    let again = Game::with_map(&MapFile::from_rgba(200, 100, &data).unwrap(), 2, 4, Fx::from_ratio(1, 10), 3).unwrap();
+    assert_eq!(again.snapshot(), game.snapshot());
+}
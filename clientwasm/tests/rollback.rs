@@ -0,0 +1,149 @@
+// running ahead of the server on a guess, and fixing it up when the real netstep shows up
+use clientwasm::sim::steer::Command;
+use clientwasm::sim::{Game, TickResult, Fx};
+use stratapaint_protocol::{NetStep, PaintUpdate};
+
+mod common;
+use common::{full, paint};
+
+// paint on a few netsteps, none on the rest
+fn updates(n: u32) -> Vec<PaintUpdate> {
+    if n % 3 == 1 {
+        vec![paint(n % 2, 20 + 10 * n, 100, 8, 8, full(Command::Attract))]
+    } else {
+        Vec::new()
+    }
+}
+
+// the hash acked for each netstep, by a game that always waits
+fn lockstep(netsteps: u32) -> (Game, Vec<u64>) {
+    let mut g = Game::new(200, 200, 2, 4, Fx::from_ratio(1, 10), 9);
+    let mut acks = Vec::new();
+    for n in 0..netsteps {
+        g.add_netstep(NetStep{n, updates: updates(n)});
+        loop {
+            match g.tick() {
+                TickResult::Waiting => break,
+                TickResult::NetStep(_) => acks.push(g.get_cur_tick().hash()),
+                _ => (),
+            }
+        }
+    }
+    (g, acks)
+}
+
+#[test]
+fn guesses_get_fixed_up() {
+    let (want, wantacks) = lockstep(12);
+
+    // every netstep shows up a netstep late
+    let mut g = Game::new(200, 200, 2, 4, Fx::from_ratio(1, 10), 9);
+    g.maxpredict = 1;
+    let mut acks = Vec::new();
+    for n in 0..12 {
+        loop {
+            match g.tick() {
+                TickResult::Waiting => break,
+                TickResult::NetStep(_) => acks.push(g.get_cur_tick().hash()),
+                TickResult::Predicted(m) => assert_eq!(m, n),
+                TickResult::Ticked => (),
+            }
+        }
+        assert_eq!(g.curtick, (n + 1) * 4);
+
+        // guessed or not, every netstep gets acked with the right hash once
+        let hash = g.add_netstep(NetStep{n, updates: updates(n)}).expect("should have guessed this netstep");
+        acks.push(hash);
+        g.prune(g.curtick);
+    }
+
+    assert_eq!(acks, wantacks);
+    assert_eq!(g.snapshot(), want.snapshot());
+}
+
+#[test]
+fn only_guesses_so_far() {
+    let mut g = Game::new(200, 200, 2, 4, Fx::from_ratio(1, 10), 9);
+    g.maxpredict = 2;
+    assert!(matches!(g.tick(), TickResult::Predicted(0)));
+    while !matches!(g.tick(), TickResult::Waiting) {}
+    assert_eq!(g.curtick, 8);
+
+    // the first one shows up, so there is room for another guess
+    g.add_netstep(NetStep{n: 0, updates: updates(0)});
+    while !matches!(g.tick(), TickResult::Waiting) {}
+    assert_eq!(g.curtick, 12);
+
+    // and already having the next one means no guess at all
+    g.add_netstep(NetStep{n: 1, updates: updates(1)});
+    g.add_netstep(NetStep{n: 2, updates: updates(2)});
+    g.add_netstep(NetStep{n: 3, updates: updates(3)});
+    assert!(matches!(g.tick(), TickResult::NetStep(3)));
+}
+
+#[test]
+fn rollback_to_any_tick() {
+    let (mut g, _) = lockstep(6);
+    let end = g.curtick;
+    let want = g.snapshot();
+    let before = g.states.len();
+
+    // going back and running the same netsteps again changes nothing
+    g.rollback(9);
+    assert_eq!(g.curtick, end);
+    assert_eq!(g.states.len(), before);
+    assert_eq!(g.snapshot(), want);
+}
+
+#[test]
+fn kept_until_confirmed() {
+    let mut g = Game::new(200, 200, 2, 4, Fx::from_ratio(1, 10), 9);
+    g.maxpredict = 2;
+    g.add_netstep(NetStep{n: 0, updates: updates(0)});
+    while !matches!(g.tick(), TickResult::Waiting) {}
+    assert_eq!(g.curtick, 12);
+
+    // the display asking to drop everything can't drop what we guessed on
+    g.prune(g.curtick);
+    assert!(g.states.iter().any(|t| t.tick == 4));
+    assert!(g.add_netstep(NetStep{n: 1, updates: updates(1)}).is_some());
+
+    g.prune(g.curtick);
+    assert!(g.states.iter().all(|t| t.tick >= 8));
+}
+
+// where the display would draw each bot, between the two ticks either side of a displayed tick
+fn shown(g: &Game, at: f32) -> Vec<(u32, f32, f32)> {
+    let t1 = g.states.iter().find(|t| t.tick == at as u32).unwrap();
+    let t2 = g.states.iter().find(|t| t.tick == at.ceil() as u32).unwrap();
+    let f = at.fract();
+    t1.bots.iter().filter_map(|b1| {
+        let b2 = t2.bots.get(b1.id)?;
+        Some((b1.id, b1.x.to_f32() + f * (b2.x - b1.x).to_f32(), b1.y.to_f32() + f * (b2.y - b1.y).to_f32()))
+    }).collect()
+}
+
+#[test]
+fn display_never_sees_a_guess() {
+    let mut g = Game::new(200, 200, 2, 4, Fx::from_ratio(1, 10), 9);
+    g.maxpredict = 1;
+    g.add_netstep(NetStep{n: 0, updates: Vec::new()});
+    while !matches!(g.tick(), TickResult::Waiting) {}
+    assert_eq!(g.curtick, 8);
+
+    // netstep 1 is a guess, so the display can only get as far as the start of it
+    assert_eq!(g.confirmed_tick(), 4);
+    let before: Vec<_> = [2.5, 3.25, 4.0].iter().map(|at| shown(&g, *at)).collect();
+    let guessed = shown(&g, 8.0);
+
+    // the guess was wrong, everything after it gets redone
+    let (x, y) = g.team_start(0, 2);
+    let (x, y) = (x.tile() - 10, y.tile() - 10);
+    g.add_netstep(NetStep{n: 1, updates: vec![paint(0, x, y, 20, 20, full(Command::Repel))]});
+    assert_ne!(shown(&g, 8.0), guessed);
+    assert_eq!(g.confirmed_tick(), 8);
+
+    // but nothing that could have been on screen moved
+    let after: Vec<_> = [2.5, 3.25, 4.0].iter().map(|at| shown(&g, *at)).collect();
+    assert_eq!(before, after);
+}
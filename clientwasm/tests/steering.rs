@@ -0,0 +1,91 @@
+// bots follow their team's paint
+use clientwasm::sim::steer::{steer, Command, Steer};
+use clientwasm::sim::{Game, GameMap, TickResult, Fx, Px};
+use stratapaint_protocol::{NetStep, PaintUpdate};
+
+mod common;
+use common::{fx, full, rect};
+
+#[test]
+fn commands_round_trip() {
+    for cmd in [Command::Attract, Command::Repel, Command::Hold, Command::Dir(0), Command::Dir(200)].iter() {
+        assert_eq!(Command::from_px(cmd.to_px(0x80)), Some((*cmd, 0x80)));
+    }
+    assert_eq!(Command::from_px(Px::CLEAR), None);
+    assert_eq!(Command::from_px(Command::Attract.to_px(0)), None);
+    assert_eq!(Command::from_px(Px::GREY), None);
+}
+
+#[test]
+fn attract_pulls_toward_paint() {
+    let mut layer = GameMap::new(50, 50);
+    assert_eq!(steer(&layer, fx(26.5), fx(20.5)), Steer::default());
+
+    layer.set(29, 20, Command::Attract.to_px(0xff));
+    let s = steer(&layer, fx(26.5), fx(20.5));
+    assert!(s.ax > Fx::ZERO);
+    assert_eq!(s.ay, Fx::ZERO);
+
+    // weaker paint pulls less
+    layer.set(29, 20, Command::Attract.to_px(0x40));
+    let weak = steer(&layer, fx(26.5), fx(20.5));
+    assert!(weak.ax > Fx::ZERO && weak.ax < s.ax);
+
+    // out of range
+    assert_eq!(steer(&layer, fx(10.5), fx(20.5)), Steer::default());
+}
+
+#[test]
+fn repel_pushes_away() {
+    let mut layer = GameMap::new(50, 50);
+    layer.set(20, 25, Command::Repel.to_px(0xff));
+    let s = steer(&layer, fx(20.5), fx(22.5));
+    assert!(s.ay < Fx::ZERO);
+    assert_eq!(s.ax, Fx::ZERO);
+}
+
+#[test]
+fn hold_and_direction_only_underneath() {
+    let mut layer = GameMap::new(50, 50);
+    layer.set(10, 10, Command::Hold.to_px(0xff));
+    layer.set(12, 10, Command::Dir(64).to_px(0xff)); // a quarter turn, so +y
+    assert!(steer(&layer, fx(10.5), fx(10.5)).hold > Fx::ZERO);
+    assert_eq!(steer(&layer, fx(11.5), fx(10.5)), Steer::default());
+
+    let s = steer(&layer, fx(12.5), fx(10.5));
+    assert_eq!(s.ax, Fx::ZERO);
+    assert!(s.ay > Fx::ZERO);
+}
+
+// how many of team 0's bots are in a box
+fn count_in(game: &Game, x0: u32, y0: u32, x1: u32, y1: u32) -> usize {
+    game.get_cur_tick().bots.iter().filter(|b| {
+        b.x.tile() >= x0 && b.x.tile() < x1 && b.y.tile() >= y0 && b.y.tile() < y1
+    }).count()
+}
+
+#[test]
+fn bots_gather_on_attract_paint() {
+    let (x, y, w, h) = (120, 90, 20, 20);
+    let paint = rect(x, y, w, h, full(Command::Attract));
+
+    let mut painted = Game::new(200, 200, 1, 4, Fx::from_ratio(1, 10), 3);
+    let mut plain = Game::new(200, 200, 1, 4, Fx::from_ratio(1, 10), 3);
+    for n in 0..30 {
+        let updates = if n == 0 {
+            vec![PaintUpdate{player: 0, delta: paint.clone()}]
+        } else {
+            Vec::new()
+        };
+        painted.add_netstep(NetStep{n, updates});
+        plain.add_netstep(NetStep{n, updates: Vec::new()});
+        while !matches!(painted.tick(), TickResult::Waiting) {}
+        while !matches!(plain.tick(), TickResult::Waiting) {}
+        painted.prune(painted.curtick);
+        plain.prune(plain.curtick);
+    }
+
+    let a = count_in(&painted, x, y, x + w, y + h);
+    let b = count_in(&plain, x, y, x + w, y + h);
+    assert!(a > b * 2, "{} bots on the paint, {} without it", a, b);
+}
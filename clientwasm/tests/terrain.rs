@@ -0,0 +1,84 @@
+// cover, mud and water on the map change how bots move and fight
+use clientwasm::sim::steer::Command;
+use clientwasm::sim::{Game, GameMap, MapTiles, Fx};
+
+mod common;
+use common::{fill, fx, paint_all, run_netstep, run_netstep_checked};
+
+#[test]
+fn encodings_round_trip() {
+    for t in [MapTiles::Wall, MapTiles::Nothing, MapTiles::Unk, MapTiles::Cover, MapTiles::Mud, MapTiles::Water].iter() {
+        let mut m = GameMap::new(1, 1);
+        m.set_tile(0, 0, *t);
+        assert_eq!(m.get_tile(0, 0), *t);
+    }
+    assert!(MapTiles::Water.blocks_move() && !MapTiles::Water.blocks_sight());
+    assert!(MapTiles::Wall.blocks_move() && MapTiles::Wall.blocks_sight());
+    assert!(!MapTiles::Mud.blocks_move() && !MapTiles::Cover.blocks_sight());
+}
+
+#[test]
+fn shots_go_over_water_not_walls() {
+    let mut game = Game::empty(100, 100, 2, 4, Fx::from_ratio(1, 10), 1);
+    fill(&mut game.map, 51, 10, 1, 20, MapTiles::Wall);
+    fill(&mut game.map, 51, 60, 1, 20, MapTiles::Water);
+    let a1 = game.spawn_bot(fx(50.5), fx(20.5), 0);
+    let b1 = game.spawn_bot(fx(52.5), fx(20.5), 1);
+    let a2 = game.spawn_bot(fx(50.5), fx(70.5), 0);
+    let b2 = game.spawn_bot(fx(52.5), fx(70.5), 1);
+
+    run_netstep(&mut game, 0, paint_all(2, 40, 0, 20, 100, Command::Hold));
+    let tk = game.get_cur_tick();
+    let t = |id: u32| tk.bots.get(id).unwrap().cur_target;
+    assert_eq!((t(a1), t(b1)), (0, 0));
+    assert_eq!((t(a2), t(b2)), (b2, a2));
+
+    // walls block sight, on the map or off a corner
+    assert!(!game.map.clear_sight(fx(50.5), fx(20.5), fx(52.5), fx(21.5)));
+    assert!(game.map.clear_sight(fx(50.5), fx(70.5), fx(52.5), fx(71.5)));
+    assert!(!game.map.clear_sight(fx(50.0), fx(31.0), fx(52.0), fx(29.0)));
+}
+
+#[test]
+fn cover_takes_less_damage() {
+    let mut game = Game::empty(100, 100, 2, 4, Fx::from_ratio(1, 10), 1);
+    fill(&mut game.map, 40, 40, 10, 20, MapTiles::Cover);
+    let a = game.spawn_bot(fx(49.5), fx(50.5), 0);
+    let b = game.spawn_bot(fx(51.5), fx(50.5), 1);
+
+    for n in 0..4 {
+        run_netstep(&mut game, n, paint_all(2, 40, 40, 20, 20, Command::Hold));
+    }
+    let tk = game.get_cur_tick();
+    let lost = |id: u32| Fx::from_int(100) - tk.bots.get(id).unwrap().health;
+    // they don't start shooting on the same tick, so only about half
+    assert!(lost(b) > Fx::ZERO);
+    assert!(lost(a) * Fx::from_int(3) < lost(b) * Fx::from_int(2));
+}
+
+#[test]
+fn mud_slows_and_water_stops() {
+    let mut game = Game::empty(200, 100, 1, 4, Fx::from_ratio(1, 10), 1);
+    fill(&mut game.map, 0, 25, 200, 50, MapTiles::Mud);
+    fill(&mut game.map, 60, 80, 2, 20, MapTiles::Water);
+    let open = game.spawn_bot(fx(20.5), fx(10.5), 0);
+    let mud = game.spawn_bot(fx(20.5), fx(50.5), 0);
+    let wet = game.spawn_bot(fx(50.5), fx(90.5), 0);
+
+    // everyone gets pushed right, but mud caps speed at 3 a second
+    let step = Fx::from_int(3) * Fx::from_ratio(1, 10);
+    run_netstep(&mut game, 0, paint_all(1, 0, 0, 200, 100, Command::Dir(0)));
+    let mut last = game.get_cur_tick().bots.get(mud).unwrap().x;
+    for n in 1..20 {
+        run_netstep_checked(&mut game, n, Vec::new(), |g| {
+            let x = g.get_cur_tick().bots.get(mud).unwrap().x;
+            assert!((x - last).abs() <= step, "mud bot went too fast");
+            last = x;
+        });
+    }
+
+    let tk = game.get_cur_tick();
+    let x = |id: u32| tk.bots.get(id).unwrap().x;
+    assert!(x(open) > x(mud) + Fx::from_int(10));
+    assert!(x(wet) < Fx::from_int(60));
+}
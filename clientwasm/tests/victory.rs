@@ -0,0 +1,78 @@
+// bases can be destroyed, and the game ends when only one team is left
+use clientwasm::sim::steer::Command;
+use clientwasm::sim::{Game, Fx};
+
+mod common;
+use common::{fx, paint_all, run_netstep};
+
+// twenty bots sitting on an enemy base
+fn siege() -> (Game, u32) {
+    let mut game = Game::empty(100, 100, 2, 4, Fx::from_ratio(1, 10), 1);
+    let base = game.spawn_base(fx(30.5), fx(30.5), 1);
+    for i in 0..20 {
+        game.spawn_bot(fx(26.5 + (i % 5) as f32 * 2.0), fx(27.5 + (i / 5) as f32 * 2.0), 0);
+    }
+    (game, base)
+}
+
+#[test]
+fn bots_destroy_a_base_and_win() {
+    let (mut game, base) = siege();
+    run_netstep(&mut game, 0, paint_all(2, 20, 20, 20, 20, Command::Hold));
+    assert!(game.get_cur_tick().bases.iter().any(|b| b.id == base));
+    assert!(game.game_over().is_none());
+
+    let mut n = 1;
+    while game.game_over().is_none() {
+        assert!(n < 200, "base never went down");
+        run_netstep(&mut game, n, Vec::new());
+        n += 1;
+    }
+
+    let tk = game.get_cur_tick();
+    assert!(tk.bases.is_empty());
+    let r = game.game_over().unwrap().clone();
+    assert_eq!(r.winner, Some(0));
+    assert!(r.tick > 0 && r.tick <= tk.tick);
+    assert_eq!(r.teams.len(), 2);
+    assert_eq!(r.teams[0].bots, tk.teambotcount[0]);
+    assert_eq!(r.teams[0].bases, 0);
+    assert_eq!(r.teams[0].spawned, 0);
+    assert_eq!(r.teams[1].bots, 0);
+    assert_eq!(r.teams[1].bases, 0);
+    // the base got some bots out before it went down, and all of them died
+    assert!(r.teams[1].spawned > 0);
+    assert_eq!(r.teams[1].lost, r.teams[1].spawned);
+
+    // nothing spawns from a dead base, and the result doesn't change
+    for m in n..n + 10 {
+        run_netstep(&mut game, m, Vec::new());
+    }
+    assert_eq!(game.get_cur_tick().teambotcount[1], 0);
+    assert_eq!(game.game_over(), Some(&r));
+}
+
+#[test]
+fn result_survives_a_snapshot() {
+    let (mut game, _) = siege();
+    run_netstep(&mut game, 0, paint_all(2, 20, 20, 20, 20, Command::Hold));
+    let mut n = 1;
+    while game.game_over().is_none() {
+        run_netstep(&mut game, n, Vec::new());
+        n += 1;
+    }
+
+    let copy = Game::from_snapshot(&game.snapshot()).unwrap();
+    assert_eq!(copy.game_over(), game.game_over());
+    assert_eq!(copy.get_cur_tick().teamstats, game.get_cur_tick().teamstats);
+    assert_eq!(copy.get_cur_tick().hash(), game.get_cur_tick().hash());
+}
+
+#[test]
+fn nobody_left_is_a_draw() {
+    let mut game = Game::empty(100, 100, 2, 4, Fx::from_ratio(1, 10), 1);
+    run_netstep(&mut game, 0, Vec::new());
+    let r = game.game_over().unwrap();
+    assert_eq!(r.tick, 1);
+    assert_eq!(r.winner, None);
+}
@@ -27,7 +27,7 @@ cargo build
 popd
 
 # copy over the server
-cp ${DIR}/server/target/debug/gameserver ${DIR}/build/
+cp ${DIR}/target/debug/gameserver ${DIR}/build/
 
 # done!
 
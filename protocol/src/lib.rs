@@ -0,0 +1,405 @@
+// every message that goes over the websocket between the server and the clients
+// both ends use the encode and decode here, so they can't disagree about the format
+//
+// messages are binary websocket frames
+// the first byte is the protocol version, the second says which message it is
+// then the message's fields in order, see wire.rs for how they are packed
+//
+// example session
+//  client : server
+//  -> join game
+//  <- game info (map file if there is one, # players ready, x Steps to 1 NetStep)
+//  -> ready
+//  <- NetStep 0 updates (probably empty)
+//  -> Ack NetStep0 (with a hash of our game state, to catch desyncs)
+//  -> queue Update
+//  <- NetStep 1 updates (should contain queued updates)
+//  <- NetStep 2 updates
+//
+// joining a game that has already started, the server sends the whole game state
+//  -> join game
+//  <- game info
+//  <- resync (game state as of the end of the last netstep sent)
+//  <- NetStep n updates
+//
+// if a client's state hash doesn't match the server's, everyone gets a desync report
+//  <- desync (netstep, expected hash, what each player had)
+//
+// when only one team is left, the server stops sending netsteps
+//  <- game over (winner, stats for each team)
+
+use std::fmt;
+
+pub mod wire;
+mod paint;
+
+use wire::Reader;
+pub use paint::{PaintDelta, Run};
+
+// bump this whenever the encoding of anything changes
+pub const VERSION: u8 = 7;
+
+pub type PlayerId = u32;
+
+#[derive(Debug,PartialEq,Eq)]
+pub enum DecodeError {
+    Empty,
+    BadVersion(u8),
+    UnknownMessage(u8),
+    MissingField(&'static str),
+    BadField(&'static str),
+    TrailingBytes,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::Empty => write!(f, "empty message"),
+            DecodeError::BadVersion(v) => write!(f, "protocol version {}, expected {}", v, VERSION),
+            DecodeError::UnknownMessage(t) => write!(f, "unknown message type {:#x}", t),
+            DecodeError::MissingField(n) => write!(f, "missing field {}", n),
+            DecodeError::BadField(n) => write!(f, "bad field {}", n),
+            DecodeError::TrailingBytes => write!(f, "extra bytes after message"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+// checks the version and pulls out the message type
+fn start_decode<'a>(b: &'a [u8]) -> Result<(u8, Reader<'a>), DecodeError> {
+    let mut rd = Reader::new(b);
+    let version = rd.u8("version").map_err(|_| DecodeError::Empty)?;
+    if version != VERSION {
+        return Err(DecodeError::BadVersion(version));
+    }
+    let tag = rd.u8("message type")?;
+    Ok((tag, rd))
+}
+
+fn start_encode(tag: u8) -> Vec<u8> {
+    let mut buf = Vec::new();
+    wire::put_u8(&mut buf, VERSION);
+    wire::put_u8(&mut buf, tag);
+    buf
+}
+
+// everything a client needs to set up the game before the first netstep
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct GameInfo {
+    pub player: PlayerId, // who the recieving client is
+    pub mapw: u32,
+    pub maph: u32,
+    pub nteams: u32, // players are on team player % nteams
+    pub seed: u32,
+    pub tickratio: u32, // ticks per netstep
+    pub tickstep: u32, // milliseconds per tick
+    pub decay: u32, // paint strength lost per game second
+    pub nready: u32,
+    pub nplayers: u32,
+    pub map: Vec<u8>, // png map file, only in the first info a player gets, empty after that or for a map made from the seed
+}
+
+impl GameInfo {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        for v in [self.player, self.mapw, self.maph, self.nteams, self.seed, self.tickratio, self.tickstep, self.decay, self.nready, self.nplayers].iter() {
+            wire::put_varint(buf, *v);
+        }
+        wire::put_bytes(buf, &self.map);
+    }
+
+    fn decode(rd: &mut Reader) -> Result<GameInfo, DecodeError> {
+        Ok(GameInfo {
+            player: rd.varint("player")?,
+            mapw: rd.varint("mapw")?,
+            maph: rd.varint("maph")?,
+            nteams: rd.varint("nteams")?,
+            seed: rd.varint("seed")?,
+            tickratio: rd.varint("tickratio")?,
+            tickstep: rd.varint("tickstep")?,
+            decay: rd.varint("decay")?,
+            nready: rd.varint("nready")?,
+            nplayers: rd.varint("nplayers")?,
+            map: rd.bytes("map")?.to_vec(),
+        })
+    }
+}
+
+// paint from a player
+// the player is filled in by the server, whatever the client sends is ignored
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct PaintUpdate {
+    pub player: PlayerId,
+    pub delta: PaintDelta,
+}
+
+impl PaintUpdate {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        wire::put_varint(buf, self.player);
+        self.delta.encode(buf);
+    }
+
+    fn decode(rd: &mut Reader) -> Result<PaintUpdate, DecodeError> {
+        Ok(PaintUpdate {
+            player: rd.varint("player")?,
+            delta: PaintDelta::decode(rd)?,
+        })
+    }
+}
+
+// all the updates to apply at the start of netstep n
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct NetStep {
+    pub n: u32,
+    pub updates: Vec<PaintUpdate>,
+}
+
+impl NetStep {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        wire::put_varint(buf, self.n);
+        wire::put_varint(buf, self.updates.len() as u32);
+        for u in self.updates.iter() {
+            u.encode(buf);
+        }
+    }
+
+    fn decode(rd: &mut Reader) -> Result<NetStep, DecodeError> {
+        let n = rd.varint("netstep")?;
+        let count = rd.count(1, "updates")?;
+        let mut updates = Vec::with_capacity(count);
+        for _ in 0..count {
+            updates.push(PaintUpdate::decode(rd)?);
+        }
+        Ok(NetStep{n, updates})
+    }
+}
+
+// sent when a player's state hash doesn't match the server's
+// only the first mismatched netstep gets reported, everything after it would be off too
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct DesyncReport {
+    pub n: u32, // the netstep
+    pub tick: u32, // the tick whose state was hashed, right after the netstep's first tick
+    pub expected: u64, // the server's hash
+    pub hashes: Vec<(PlayerId, u64)>, // what the players that have acked the netstep had
+}
+
+impl DesyncReport {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        wire::put_varint(buf, self.n);
+        wire::put_varint(buf, self.tick);
+        wire::put_u64(buf, self.expected);
+        wire::put_varint(buf, self.hashes.len() as u32);
+        for (p, h) in self.hashes.iter() {
+            wire::put_varint(buf, *p);
+            wire::put_u64(buf, *h);
+        }
+    }
+
+    fn decode(rd: &mut Reader) -> Result<DesyncReport, DecodeError> {
+        let n = rd.varint("netstep")?;
+        let tick = rd.varint("tick")?;
+        let expected = rd.u64("expected hash")?;
+        let count = rd.count(9, "hashes")?;
+        let mut hashes = Vec::with_capacity(count);
+        for _ in 0..count {
+            hashes.push((rd.varint("player")?, rd.u64("hash")?));
+        }
+        Ok(DesyncReport{n, tick, expected, hashes})
+    }
+}
+
+// how a team finished
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct TeamResult {
+    pub bots: u32, // left at the end
+    pub bases: u32,
+    pub spawned: u32, // from bases over the whole game
+    pub lost: u32, // bots killed
+}
+
+// the end of a game
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct GameResult {
+    pub tick: u32, // the tick it ended on
+    pub winner: Option<u32>, // the team left standing, None if nobody was
+    pub teams: Vec<TeamResult>,
+}
+
+impl GameResult {
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        wire::put_varint(buf, self.tick);
+        // 0 for no winner, otherwise team + 1
+        wire::put_varint(buf, self.winner.map_or(0, |w| w + 1));
+        wire::put_varint(buf, self.teams.len() as u32);
+        for t in self.teams.iter() {
+            for v in [t.bots, t.bases, t.spawned, t.lost].iter() {
+                wire::put_varint(buf, *v);
+            }
+        }
+    }
+
+    pub fn decode(rd: &mut Reader) -> Result<GameResult, DecodeError> {
+        let tick = rd.varint("tick")?;
+        let winner = match rd.varint("winner")? {
+            0 => None,
+            w => Some(w - 1),
+        };
+        let count = rd.count(4, "teams")?;
+        let mut teams = Vec::with_capacity(count);
+        for _ in 0..count {
+            teams.push(TeamResult {
+                bots: rd.varint("bots")?,
+                bases: rd.varint("bases")?,
+                spawned: rd.varint("spawned")?,
+                lost: rd.varint("lost")?,
+            });
+        }
+        if matches!(winner, Some(w) if w as usize >= teams.len()) {
+            return Err(DecodeError::BadField("winner"));
+        }
+        Ok(GameResult{tick, winner, teams})
+    }
+}
+
+// message types
+const TAG_PING: u8 = 0x01;
+const TAG_JOIN: u8 = 0x02;
+const TAG_READY: u8 = 0x03;
+const TAG_ACK: u8 = 0x04;
+const TAG_PAINT: u8 = 0x05;
+const TAG_WANTRESYNC: u8 = 0x06;
+
+const TAG_PONG: u8 = 0x81;
+const TAG_INFO: u8 = 0x82;
+const TAG_STEP: u8 = 0x83;
+const TAG_ERROR: u8 = 0x84;
+const TAG_RESYNC: u8 = 0x85;
+const TAG_DESYNC: u8 = 0x86;
+const TAG_GAMEOVER: u8 = 0x87;
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum ClientMsg {
+    Ping,
+    Join(String), // game name
+    Ready,
+    Ack(u32, u64), // netstep number, hash of the state after the netstep's first tick
+    Paint(PaintUpdate), // queue an update for the next netstep
+    WantResync, // ask for the full game state
+}
+
+impl ClientMsg {
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            ClientMsg::Ping => start_encode(TAG_PING),
+            ClientMsg::Join(game) => {
+                let mut buf = start_encode(TAG_JOIN);
+                wire::put_str(&mut buf, game);
+                buf
+            },
+            ClientMsg::Ready => start_encode(TAG_READY),
+            ClientMsg::Ack(n, hash) => {
+                let mut buf = start_encode(TAG_ACK);
+                wire::put_varint(&mut buf, *n);
+                wire::put_u64(&mut buf, *hash);
+                buf
+            },
+            ClientMsg::Paint(p) => {
+                let mut buf = start_encode(TAG_PAINT);
+                p.encode(&mut buf);
+                buf
+            },
+            ClientMsg::WantResync => start_encode(TAG_WANTRESYNC),
+        }
+    }
+
+    pub fn decode(b: &[u8]) -> Result<ClientMsg, DecodeError> {
+        let (tag, mut rd) = start_decode(b)?;
+
+        let msg = match tag {
+            TAG_PING => ClientMsg::Ping,
+            TAG_JOIN => {
+                let game = rd.str("game")?;
+                if game.is_empty() {
+                    return Err(DecodeError::BadField("game"));
+                }
+                ClientMsg::Join(game)
+            },
+            TAG_READY => ClientMsg::Ready,
+            TAG_ACK => ClientMsg::Ack(rd.varint("netstep")?, rd.u64("hash")?),
+            TAG_PAINT => ClientMsg::Paint(PaintUpdate::decode(&mut rd)?),
+            TAG_WANTRESYNC => ClientMsg::WantResync,
+            _ => return Err(DecodeError::UnknownMessage(tag)),
+        };
+
+        rd.done()?;
+        Ok(msg)
+    }
+}
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum ServerMsg {
+    Pong,
+    Info(GameInfo),
+    Step(NetStep),
+    Error(String),
+    Resync(Vec<u8>), // full game state, the next netstep picks up from there
+    Desync(DesyncReport),
+    GameOver(GameResult),
+}
+
+impl ServerMsg {
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            ServerMsg::Pong => start_encode(TAG_PONG),
+            ServerMsg::Info(i) => {
+                let mut buf = start_encode(TAG_INFO);
+                i.encode(&mut buf);
+                buf
+            },
+            ServerMsg::Step(st) => {
+                let mut buf = start_encode(TAG_STEP);
+                st.encode(&mut buf);
+                buf
+            },
+            ServerMsg::Error(e) => {
+                let mut buf = start_encode(TAG_ERROR);
+                wire::put_str(&mut buf, e);
+                buf
+            },
+            ServerMsg::Resync(state) => {
+                let mut buf = start_encode(TAG_RESYNC);
+                wire::put_bytes(&mut buf, state);
+                buf
+            },
+            ServerMsg::Desync(r) => {
+                let mut buf = start_encode(TAG_DESYNC);
+                r.encode(&mut buf);
+                buf
+            },
+            ServerMsg::GameOver(r) => {
+                let mut buf = start_encode(TAG_GAMEOVER);
+                r.encode(&mut buf);
+                buf
+            },
+        }
+    }
+
+    pub fn decode(b: &[u8]) -> Result<ServerMsg, DecodeError> {
+        let (tag, mut rd) = start_decode(b)?;
+
+        let msg = match tag {
+            TAG_PONG => ServerMsg::Pong,
+            TAG_INFO => ServerMsg::Info(GameInfo::decode(&mut rd)?),
+            TAG_STEP => ServerMsg::Step(NetStep::decode(&mut rd)?),
+            TAG_ERROR => ServerMsg::Error(rd.str("error")?),
+            TAG_RESYNC => ServerMsg::Resync(rd.bytes("state")?.to_vec()),
+            TAG_DESYNC => ServerMsg::Desync(DesyncReport::decode(&mut rd)?),
+            TAG_GAMEOVER => ServerMsg::GameOver(GameResult::decode(&mut rd)?),
+            _ => return Err(DecodeError::UnknownMessage(tag)),
+        };
+
+        rd.done()?;
+        Ok(msg)
+    }
+}
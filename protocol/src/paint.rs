@@ -0,0 +1,156 @@
+// paint deltas, a changed region of a paint layer
+// stored and sent as runs over the region's pixels in row major order
+// so big strokes of one color, and the untouched space around them, stay small
+
+use crate::DecodeError;
+use crate::wire::{self, Reader};
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Run {
+    Skip(u32), // leave this many pixels as they are
+    Fill(u32, u32), // set this many pixels to the color (0xRRGGBBAA)
+}
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct PaintDelta {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+    pub runs: Vec<Run>, // never covers more than w * h pixels, anything after the runs is skipped
+}
+
+impl PaintDelta {
+    // an empty delta over a region
+    pub fn new(x: u32, y: u32, w: u32, h: u32) -> PaintDelta {
+        PaintDelta {
+            x,
+            y,
+            w,
+            h,
+            runs: Vec::new(),
+        }
+    }
+
+    // px is w * h row major pixels for the region, None for pixels left alone
+    pub fn from_pixels(x: u32, y: u32, w: u32, h: u32, px: &[Option<u32>]) -> PaintDelta {
+        if px.len() != (w as usize) * (h as usize) {
+            panic!("Tried to make a PaintDelta from the wrong number of pixels");
+        }
+
+        let mut d = PaintDelta::new(x, y, w, h);
+        for p in px {
+            d.push(*p);
+        }
+        d.trim();
+        d
+    }
+
+    // add the next pixel in the region to the runs
+    fn push(&mut self, p: Option<u32>) {
+        match (self.runs.last_mut(), p) {
+            (Some(Run::Skip(n)), None) => *n += 1,
+            (Some(Run::Fill(n, c)), Some(color)) if *c == color => *n += 1,
+            (_, None) => self.runs.push(Run::Skip(1)),
+            (_, Some(color)) => self.runs.push(Run::Fill(1, color)),
+        }
+    }
+
+    // trailing skips don't do anything
+    fn trim(&mut self) {
+        if let Some(Run::Skip(_)) = self.runs.last() {
+            self.runs.pop();
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.runs.iter().all(|r| matches!(r, Run::Skip(_)))
+    }
+
+    // calls f(x, y, color) for every pixel set by the delta, in row major order
+    pub fn for_each<F: FnMut(u32, u32, u32)>(&self, mut f: F) {
+        if self.w == 0 {
+            return;
+        }
+
+        let mut i: u64 = 0;
+        for r in self.runs.iter() {
+            match *r {
+                Run::Skip(n) => {
+                    i += n as u64;
+                },
+                Run::Fill(n, color) => {
+                    for _ in 0..n {
+                        let px = (i % (self.w as u64)) as u32;
+                        let py = (i / (self.w as u64)) as u32;
+                        f(self.x + px, self.y + py, color);
+                        i += 1;
+                    }
+                },
+            }
+        }
+    }
+
+    // expands back out to w * h row major pixels
+    pub fn pixels(&self) -> Vec<Option<u32>> {
+        let mut px = vec![None; (self.w as usize) * (self.h as usize)];
+        self.for_each(|x, y, color| {
+            px[((x - self.x) + ((y - self.y) * self.w)) as usize] = Some(color);
+        });
+        px
+    }
+
+    // each run is a varint of (length << 1 | is_fill), fills are followed by the color
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        wire::put_varint(buf, self.x);
+        wire::put_varint(buf, self.y);
+        wire::put_varint(buf, self.w);
+        wire::put_varint(buf, self.h);
+        wire::put_varint(buf, self.runs.len() as u32);
+        for r in self.runs.iter() {
+            match *r {
+                Run::Skip(n) => {
+                    wire::put_varint(buf, n << 1);
+                },
+                Run::Fill(n, color) => {
+                    wire::put_varint(buf, (n << 1) | 1);
+                    wire::put_u32(buf, color);
+                },
+            }
+        }
+    }
+
+    pub fn decode(rd: &mut Reader) -> Result<PaintDelta, DecodeError> {
+        let x = rd.varint("delta x")?;
+        let y = rd.varint("delta y")?;
+        let w = rd.varint("delta w")?;
+        let h = rd.varint("delta h")?;
+        let nruns = rd.count(1, "delta runs")?;
+
+        let area = (w as u64) * (h as u64);
+        let mut total: u64 = 0;
+        let mut runs = Vec::with_capacity(nruns);
+        for _ in 0..nruns {
+            let v = rd.varint("run")?;
+            let n = v >> 1;
+            total += n as u64;
+            if total > area {
+                return Err(DecodeError::BadField("run"));
+            }
+
+            if v & 1 == 0 {
+                runs.push(Run::Skip(n));
+            } else {
+                runs.push(Run::Fill(n, rd.u32("run color")?));
+            }
+        }
+
+        Ok(PaintDelta {
+            x,
+            y,
+            w,
+            h,
+            runs,
+        })
+    }
+}
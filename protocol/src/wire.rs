@@ -0,0 +1,127 @@
+// low level pieces of the binary encoding
+// numbers are LEB128 varints, so small values (most of ours) take one byte
+// strings are a varint length followed by the utf8 bytes
+// public so things carried as opaque bytes (like game snapshots) can be packed the same way
+
+use crate::DecodeError;
+
+pub fn put_u8(buf: &mut Vec<u8>, v: u8) {
+    buf.push(v);
+}
+
+pub fn put_varint(buf: &mut Vec<u8>, mut v: u32) {
+    loop {
+        let b = (v & 0x7f) as u8;
+        v >>= 7;
+        if v == 0 {
+            buf.push(b);
+            return;
+        }
+        buf.push(b | 0x80);
+    }
+}
+
+pub fn put_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_be_bytes());
+}
+
+pub fn put_u64(buf: &mut Vec<u8>, v: u64) {
+    buf.extend_from_slice(&v.to_be_bytes());
+}
+
+pub fn put_bytes(buf: &mut Vec<u8>, v: &[u8]) {
+    put_varint(buf, v.len() as u32);
+    buf.extend_from_slice(v);
+}
+
+pub fn put_str(buf: &mut Vec<u8>, v: &str) {
+    put_bytes(buf, v.as_bytes());
+}
+
+pub struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Reader<'a> {
+        Reader {
+            buf,
+            pos: 0,
+        }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    pub fn done(&self) -> Result<(), DecodeError> {
+        if self.remaining() != 0 {
+            return Err(DecodeError::TrailingBytes);
+        }
+        Ok(())
+    }
+
+    pub fn u8(&mut self, name: &'static str) -> Result<u8, DecodeError> {
+        let b = *self.buf.get(self.pos).ok_or(DecodeError::MissingField(name))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    pub fn varint(&mut self, name: &'static str) -> Result<u32, DecodeError> {
+        let mut v: u32 = 0;
+        for i in 0..5 {
+            let b = self.u8(name)?;
+            let part = (b & 0x7f) as u32;
+            if i == 4 && part > 0xf {
+                // would overflow a u32
+                return Err(DecodeError::BadField(name));
+            }
+            v |= part << (7 * i);
+            if b & 0x80 == 0 {
+                return Ok(v);
+            }
+        }
+        Err(DecodeError::BadField(name))
+    }
+
+    pub fn u32(&mut self, name: &'static str) -> Result<u32, DecodeError> {
+        let b = self.take(4, name)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub fn u64(&mut self, name: &'static str) -> Result<u64, DecodeError> {
+        let b = self.take(8, name)?;
+        Ok(u64::from_be_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+    }
+
+    // a length for a list of things each at least min_sz bytes
+    // so a bad length can't make us allocate more than the message could hold
+    pub fn count(&mut self, min_sz: usize, name: &'static str) -> Result<usize, DecodeError> {
+        let n = self.varint(name)? as usize;
+        if n.saturating_mul(min_sz) > self.remaining() {
+            return Err(DecodeError::BadField(name));
+        }
+        Ok(n)
+    }
+
+    pub fn bytes(&mut self, name: &'static str) -> Result<&'a [u8], DecodeError> {
+        let n = self.count(1, name)?;
+        self.take(n, name)
+    }
+
+    pub fn str(&mut self, name: &'static str) -> Result<String, DecodeError> {
+        let b = self.bytes(name)?;
+        let s = std::str::from_utf8(b).map_err(|_| DecodeError::BadField(name))?;
+        Ok(s.to_string())
+    }
+
+    fn take(&mut self, n: usize, name: &'static str) -> Result<&'a [u8], DecodeError> {
+        if n > self.remaining() {
+            return Err(DecodeError::MissingField(name));
+        }
+        let b = &self.buf[self.pos..(self.pos + n)];
+        self.pos += n;
+        Ok(b)
+    }
+}
@@ -0,0 +1,202 @@
+use stratapaint_protocol::*;
+
+// a couple of strokes on an 800x800 layer, like a player painting a path and an area
+fn strokes() -> NetStep {
+    let mut updates = Vec::new();
+
+    // a filled circle
+    let (cx, cy, rad) = (200i32, 300i32, 40i32);
+    let w = (rad * 2 + 1) as u32;
+    let mut px = Vec::new();
+    for y in -rad..=rad {
+        for x in -rad..=rad {
+            px.push(if (x * x) + (y * y) <= rad * rad { Some(0xff0000ffu32) } else { None });
+        }
+    }
+    updates.push(PaintUpdate {
+        player: 1,
+        delta: PaintDelta::from_pixels((cx - rad) as u32, (cy - rad) as u32, w, w, &px),
+    });
+
+    // a wide rectangle
+    let px = vec![Some(0x00ff0080u32); 300 * 20];
+    updates.push(PaintUpdate {
+        player: 2,
+        delta: PaintDelta::from_pixels(400, 600, 300, 20, &px),
+    });
+
+    // a big region with scattered changes and an erased patch
+    let mut px = vec![None; 800 * 100];
+    for (i, p) in px.iter_mut().enumerate() {
+        if i % 97 == 0 {
+            *p = Some(0x0000ffff);
+        } else if (i % 800) < 10 {
+            *p = Some(0);
+        }
+    }
+    updates.push(PaintUpdate {
+        player: 3,
+        delta: PaintDelta::from_pixels(0, 0, 800, 100, &px),
+    });
+
+    NetStep {
+        n: 1234,
+        updates,
+    }
+}
+
+// what we would send if we just used json for the same netstep
+fn json_size(st: &NetStep) -> usize {
+    let updates: Vec<serde_json::Value> = st.updates.iter().map(|u| {
+        serde_json::json!({
+            "player": u.player,
+            "x": u.delta.x,
+            "y": u.delta.y,
+            "w": u.delta.w,
+            "h": u.delta.h,
+            "px": u.delta.pixels(),
+        })
+    }).collect();
+
+    let v = serde_json::json!({
+        "n": st.n,
+        "updates": updates,
+    });
+    serde_json::to_vec(&v).unwrap().len()
+}
+
+#[test]
+fn client_msgs_round_trip() {
+    let msgs = vec![
+        ClientMsg::Ping,
+        ClientMsg::Join(String::from("some game")),
+        ClientMsg::Ready,
+        ClientMsg::Ack(0, 0),
+        ClientMsg::Ack(u32::MAX, u64::MAX),
+        ClientMsg::Paint(strokes().updates[0].clone()),
+        ClientMsg::WantResync,
+    ];
+
+    for m in msgs {
+        let b = m.encode();
+        assert_eq!(b[0], VERSION);
+        assert_eq!(ClientMsg::decode(&b), Ok(m));
+    }
+}
+
+#[test]
+fn server_msgs_round_trip() {
+    let msgs = vec![
+        ServerMsg::Pong,
+        ServerMsg::Info(GameInfo {
+            player: 3,
+            mapw: 800,
+            maph: 800,
+            nteams: 2,
+            seed: 0xdeadbeef,
+            tickratio: 4,
+            tickstep: 100,
+            decay: 4,
+            nready: 1,
+            nplayers: 2,
+            map: Vec::new(),
+        }),
+        ServerMsg::Info(GameInfo {
+            player: 0,
+            mapw: 3,
+            maph: 2,
+            nteams: 1,
+            seed: 1,
+            tickratio: 4,
+            tickstep: 100,
+            decay: 0,
+            nready: 0,
+            nplayers: 1,
+            map: vec![0x89, b'P', b'N', b'G', 0, 0xff],
+        }),
+        ServerMsg::Step(NetStep{n: 0, updates: Vec::new()}),
+        ServerMsg::Step(strokes()),
+        ServerMsg::Error(String::from("bad message")),
+        ServerMsg::Resync(vec![0, 1, 2, 0xff]),
+        ServerMsg::Desync(DesyncReport {
+            n: 12,
+            tick: 48,
+            expected: 0x0123456789abcdef,
+            hashes: vec![(0, 0x0123456789abcdef), (2, 7)],
+        }),
+        ServerMsg::GameOver(GameResult {
+            tick: 9000,
+            winner: Some(1),
+            teams: vec![
+                TeamResult{bots: 0, bases: 0, spawned: 40, lost: 790},
+                TeamResult{bots: 312, bases: 1, spawned: 120, lost: 558},
+            ],
+        }),
+        ServerMsg::GameOver(GameResult{tick: 5, winner: None, teams: Vec::new()}),
+    ];
+
+    for m in msgs {
+        let b = m.encode();
+        assert_eq!(ServerMsg::decode(&b), Ok(m));
+    }
+}
+
+#[test]
+fn delta_pixels_round_trip() {
+    for u in strokes().updates {
+        let d = &u.delta;
+        let px = d.pixels();
+        assert_eq!(PaintDelta::from_pixels(d.x, d.y, d.w, d.h, &px), *d);
+    }
+
+    let d = PaintDelta::from_pixels(5, 6, 3, 2, &[None, Some(1), Some(1), Some(2), None, None]);
+    assert_eq!(d.runs, vec![Run::Skip(1), Run::Fill(2, 1), Run::Fill(1, 2)]);
+
+    let mut set = Vec::new();
+    d.for_each(|x, y, c| set.push((x, y, c)));
+    assert_eq!(set, vec![(6, 6, 1), (7, 6, 1), (5, 7, 2)]);
+
+    assert!(PaintDelta::from_pixels(0, 0, 2, 2, &[None; 4]).is_empty());
+}
+
+#[test]
+fn rejects_bad_messages() {
+    assert_eq!(ServerMsg::decode(&[]), Err(DecodeError::Empty));
+
+    let mut b = ServerMsg::Pong.encode();
+    b[0] = VERSION + 1;
+    assert_eq!(ServerMsg::decode(&b), Err(DecodeError::BadVersion(VERSION + 1)));
+
+    assert_eq!(ServerMsg::decode(&[VERSION, 0x7f]), Err(DecodeError::UnknownMessage(0x7f)));
+    assert_eq!(ClientMsg::decode(&ServerMsg::Pong.encode()), Err(DecodeError::UnknownMessage(0x81)));
+
+    let mut b = ClientMsg::Ready.encode();
+    b.push(0);
+    assert_eq!(ClientMsg::decode(&b), Err(DecodeError::TrailingBytes));
+
+    // every truncation of a netstep should fail cleanly
+    let b = ServerMsg::Step(strokes()).encode();
+    for i in 0..b.len() {
+        assert!(ServerMsg::decode(&b[..i]).is_err());
+    }
+
+    // runs covering more than the region
+    let mut b = vec![VERSION, 0x05];
+    for v in [0u8, 0, 0, 2, 2, 1, 5 << 1].iter() {
+        b.push(*v);
+    }
+    assert_eq!(ClientMsg::decode(&b), Err(DecodeError::BadField("run")));
+}
+
+#[test]
+fn smaller_than_json() {
+    let st = strokes();
+    let bin = ServerMsg::Step(st.clone()).encode().len();
+    let json = json_size(&st);
+
+    println!("netstep binary: {} bytes, json: {} bytes", bin, json);
+    assert!(bin * 50 < json);
+
+    // an empty netstep, the common case, should be tiny
+    assert!(ServerMsg::Step(NetStep{n: 1234, updates: Vec::new()}).encode().len() <= 5);
+}
@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+use crate::msg::{BrokerMsg, GameMsg};
+use crate::game;
+
+// the broker is the one long lived task that knows about every game
+// clients ask it to join a game by name, and it hands them off to that game's task
+// starting a new game task if there isn't one yet, on the server's map if it has one
+pub async fn run(mut rx: mpsc::UnboundedReceiver<BrokerMsg>, tx: mpsc::UnboundedSender<BrokerMsg>, map: Option<Arc<Vec<u8>>>) {
+    // name -> (generation, game task chan)
+    // the generation lets us tell a stale GameClosed from a game started again under the same name
+    let mut games: HashMap<String, (u32, mpsc::UnboundedSender<GameMsg>)> = HashMap::new();
+    let mut nextgen: u32 = 0;
+
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            BrokerMsg::Join{game: name, client, reply} => {
+                let mut gmsg = GameMsg::Join{client, reply};
+
+                // try the existing game first
+                // if it has shut down, the message comes back and we start a new one
+                if let Some((_, gtx)) = games.get(&name) {
+                    match gtx.send(gmsg) {
+                        Ok(()) => continue,
+                        Err(mpsc::error::SendError(m)) => {
+                            gmsg = m;
+                        },
+                    }
+                }
+
+                println!("Starting game {}", name);
+                let (gtx, grx) = mpsc::unbounded_channel();
+                tokio::spawn(game::run(name.clone(), nextgen, map.clone(), grx, tx.clone()));
+
+                if gtx.send(gmsg).is_err() {
+                    println!("New game {} closed before we could join it", name);
+                }
+                games.insert(name, (nextgen, gtx));
+                nextgen = nextgen.wrapping_add(1);
+            },
+            BrokerMsg::GameClosed(name, gen) => {
+                let current = match games.get(&name) {
+                    Some((g, _)) => *g == gen,
+                    None => false,
+                };
+                if current {
+                    println!("Game {} closed", name);
+                    games.remove(&name);
+                }
+            },
+        }
+    }
+}
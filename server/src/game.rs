@@ -0,0 +1,352 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+use stratapaint_protocol::{DesyncReport, GameInfo, NetStep, ServerMsg, PaintUpdate, PlayerId};
+use clientwasm::sim;
+
+use crate::msg::{BrokerMsg, GameMsg, GameHandle};
+
+// game constants, sent to clients in the game info
+const MAPW: u32 = 800; // for games making up their own map
+const MAPH: u32 = 800;
+const NTEAMS: u32 = 2;
+const TICKRATIO: u32 = 4; // ticks per netstep
+const TICKSTEP: u32 = 100; // ms per tick
+const DECAY: u32 = sim::DECAY; // paint strength lost per game second
+
+// how many netsteps we will send past the slowest player's ack before waiting on them
+const MAXAHEAD: u32 = 8;
+
+// most area one player can paint in a netstep, a few of the biggest strokes, so nobody can flood everyone else
+const MAXPAINT: u64 = 4 * sim::MAXPAINTAREA;
+
+struct Player {
+    tx: mpsc::UnboundedSender<ServerMsg>,
+    ready: bool,
+    acked: Option<u32>, // last netstep acked
+    desynced: bool, // their hashes stopped matching ours, and they haven't resynced yet
+    gotmap: bool, // the map file only goes out in the first info they get
+    painted: u64, // area of their updates queued for the next netstep
+}
+
+struct Game {
+    name: String,
+    players: BTreeMap<PlayerId, Player>,
+    nextplayer: PlayerId,
+    seed: u32,
+    map: Option<Arc<Vec<u8>>>, // png map file everyone plays on, none to make one up from the seed
+    started: bool,
+    netstep: u32, // next netstep to send
+    queued: Vec<PaintUpdate>, // updates to go out in the next netstep
+    sim: sim::Game, // our own copy of the game, run up to the end of the last netstep sent
+    hashes: BTreeMap<u32, u64>, // our state hash for each netstep not everyone has acked yet
+    acks: BTreeMap<u32, Vec<(PlayerId, u64)>>, // what players had for those netsteps, for desync reports
+}
+
+impl Game {
+    fn info(&self, player: PlayerId, withmap: bool) -> GameInfo {
+        GameInfo {
+            player,
+            mapw: self.sim.map.w,
+            maph: self.sim.map.h,
+            nteams: NTEAMS,
+            seed: self.seed,
+            tickratio: TICKRATIO,
+            tickstep: TICKSTEP,
+            decay: DECAY,
+            nready: self.players.values().filter(|p| p.ready).count() as u32,
+            nplayers: self.players.len() as u32,
+            map: match &self.map {
+                Some(m) if withmap => m.to_vec(),
+                _ => Vec::new(),
+            },
+        }
+    }
+
+    fn send_info(&mut self) {
+        for (id, p) in self.players.iter() {
+            let _ = p.tx.send(ServerMsg::Info(self.info(*id, !p.gotmap)));
+        }
+        self.players.values_mut().for_each(|p| p.gotmap = true);
+    }
+
+    fn broadcast(&self, msg: ServerMsg) {
+        for p in self.players.values() {
+            // a failed send means the client task is gone, it will send a Leave
+            let _ = p.tx.send(msg.clone());
+        }
+    }
+
+    fn join(&mut self, client: mpsc::UnboundedSender<ServerMsg>) -> PlayerId {
+        let player = self.nextplayer;
+        self.nextplayer += 1;
+
+        self.players.insert(player, Player {
+            tx: client,
+            ready: self.started,
+            // late joiners start from the snapshot, so count them as having everything before it
+            acked: self.netstep.checked_sub(1),
+            desynced: false,
+            gotmap: false,
+            painted: 0,
+        });
+        self.send_info();
+
+        if self.started {
+            self.resync(player);
+        }
+        if let Some(r) = self.sim.game_over() {
+            let _ = self.players[&player].tx.send(ServerMsg::GameOver(r.clone()));
+        }
+
+        player
+    }
+
+    // send the full state of the game, for late joiners or clients that lost track
+    fn resync(&mut self, player: PlayerId) {
+        if !self.started {
+            return;
+        }
+        if let Some(p) = self.players.get_mut(&player) {
+            p.desynced = false;
+            let _ = p.tx.send(ServerMsg::Resync(self.sim.snapshot()));
+        }
+    }
+
+    fn ready(&mut self, player: PlayerId) {
+        if self.started {
+            return;
+        }
+        if let Some(p) = self.players.get_mut(&player) {
+            p.ready = true;
+        }
+        self.send_info();
+        self.try_start();
+    }
+
+    // a player is gone, false once nobody is left and the game should close
+    fn leave(&mut self, player: PlayerId) -> bool {
+        self.players.remove(&player);
+        if self.players.is_empty() {
+            return false;
+        }
+        if !self.started {
+            self.send_info();
+            self.try_start();
+        }
+        true
+    }
+
+    fn try_start(&mut self) {
+        if !self.started && !self.players.is_empty() && self.players.values().all(|p| p.ready) {
+            println!("Starting {} with {} players", self.name, self.players.len());
+            self.started = true;
+            // netstep 0 goes out right away
+            self.step();
+        }
+    }
+
+    fn ack(&mut self, player: PlayerId, n: u32, hash: u64) {
+        if n >= self.netstep {
+            // can't ack something we haven't sent
+            return;
+        }
+        let p = match self.players.get_mut(&player) {
+            Some(p) => p,
+            None => return,
+        };
+        if p.acked < Some(n) {
+            p.acked = Some(n);
+        }
+
+        // acks for netsteps everyone already acked (or from before a resync) have nothing to check
+        let expected = match self.hashes.get(&n) {
+            Some(h) => *h,
+            None => return,
+        };
+        let acks = self.acks.entry(n).or_default();
+        acks.push((player, hash));
+
+        if hash == expected || p.desynced {
+            return;
+        }
+
+        // they are going to be wrong from here on, so only report the first one
+        p.desynced = true;
+        let report = DesyncReport {
+            n,
+            tick: n * TICKRATIO + 1,
+            expected,
+            hashes: acks.clone(),
+        };
+        println!("Player {} desynced from {} at netstep {}", player, self.name, n);
+        self.broadcast(ServerMsg::Desync(report));
+    }
+
+    fn paint(&mut self, player: PlayerId, mut p: PaintUpdate) {
+        if !self.started || self.sim.game_over().is_some() {
+            return;
+        }
+        let pl = match self.players.get_mut(&player) {
+            Some(pl) => pl,
+            None => return,
+        };
+        if let Err(e) = self.sim.check_paint(&p.delta) {
+            let _ = pl.tx.send(ServerMsg::Error(format!("Bad paint: {}", e)));
+            return;
+        }
+        let area = (p.delta.w as u64) * (p.delta.h as u64);
+        if pl.painted + area > MAXPAINT {
+            let _ = pl.tx.send(ServerMsg::Error("Too much paint, slow down".to_string()));
+            return;
+        }
+        pl.painted += area;
+        p.player = player;
+        self.queued.push(p);
+    }
+
+    // send out the next netstep, unless someone is too far behind
+    fn step(&mut self) {
+        // nothing more to send once it is over, clients stop at the end of the last netstep
+        if self.sim.game_over().is_some() {
+            return;
+        }
+        let slowest = self.players.values().map(|p| p.acked.map_or(0, |a| a + 1)).min().unwrap_or(0);
+        if self.netstep >= slowest + MAXAHEAD {
+            return;
+        }
+
+        let st = NetStep {
+            n: self.netstep,
+            updates: self.queued.drain(..).collect(),
+        };
+        self.players.values_mut().for_each(|p| p.painted = 0);
+        self.netstep += 1;
+        self.broadcast(ServerMsg::Step(st.clone()));
+
+        // run our copy through the netstep, it stops when it needs the next one
+        self.sim.add_netstep(st);
+        loop {
+            match self.sim.tick() {
+                sim::TickResult::Waiting => break,
+                // we never guess, the server's sim is always in lockstep
+                sim::TickResult::Ticked | sim::TickResult::Predicted(_) => (),
+                sim::TickResult::NetStep(n) => {
+                    self.hashes.insert(n, self.sim.get_cur_tick().hash());
+                },
+            }
+        }
+        self.sim.prune(self.sim.curtick);
+
+        // everyone is past these
+        self.hashes = self.hashes.split_off(&slowest);
+        self.acks = self.acks.split_off(&slowest);
+
+        if let Some(r) = self.sim.game_over() {
+            match r.winner {
+                Some(w) => println!("Game {} over at tick {}, team {} wins", self.name, r.tick, w),
+                None => println!("Game {} over at tick {}, nobody wins", self.name, r.tick),
+            }
+            self.broadcast(ServerMsg::GameOver(r.clone()));
+        }
+    }
+}
+
+// read a png map file, and make sure games can be played on it
+pub fn load_map(path: &str) -> Result<Arc<Vec<u8>>, String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    let mf = sim::MapFile::from_png(&data).map_err(|e| e.to_string())?;
+    mf.check_teams(NTEAMS).map_err(|e| e.to_string())?;
+    Ok(Arc::new(data))
+}
+
+// one of these runs per game
+// collects input from the players in the game, and sends it out to all of them each netstep
+pub async fn run(name: String, gen: u32, map: Option<Arc<Vec<u8>>>, mut rx: mpsc::UnboundedReceiver<GameMsg>, broker: mpsc::UnboundedSender<BrokerMsg>) {
+    let seed = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.subsec_nanos());
+    let tickstep = sim::Fx::from_ratio(TICKSTEP as i32, 1000);
+    let mut sim = match &map {
+        // already checked in load_map
+        Some(m) => sim::Game::with_map(&sim::MapFile::from_png(m).unwrap(), NTEAMS, TICKRATIO, tickstep, seed).unwrap(),
+        None => sim::Game::new(MAPW, MAPH, NTEAMS, TICKRATIO, tickstep, seed),
+    };
+    sim.decay = DECAY;
+    let mut g = Game {
+        name: name.clone(),
+        players: BTreeMap::new(),
+        nextplayer: 0,
+        seed,
+        map,
+        started: false,
+        netstep: 0,
+        queued: Vec::new(),
+        sim,
+        hashes: BTreeMap::new(),
+        acks: BTreeMap::new(),
+    };
+    let mut ticker = tokio::time::interval(Duration::from_millis((TICKRATIO * TICKSTEP) as u64));
+
+    // self handle, given out to players that join
+    let (selftx, mut selfrx) = mpsc::unbounded_channel();
+
+    loop {
+        let msg = tokio::select! {
+            m = rx.recv() => m,
+            m = selfrx.recv() => m,
+            _ = ticker.tick() => {
+                if g.started {
+                    g.step();
+                }
+                continue;
+            },
+        };
+
+        // the broker keeps its sender for as long as we are registered
+        let msg = match msg {
+            Some(m) => m,
+            None => break,
+        };
+
+        match msg {
+            GameMsg::Join{client, reply} => {
+                let player = g.join(client);
+
+                let handle = GameHandle {
+                    name: name.clone(),
+                    player,
+                    tx: selftx.clone(),
+                };
+                if reply.send(handle).is_err() {
+                    println!("Player left {} before joining", name);
+                    if !g.leave(player) {
+                        break;
+                    }
+                    continue;
+                }
+
+                println!("Player {} joined {}", player, name);
+            },
+            GameMsg::Ready(player) => g.ready(player),
+            GameMsg::Ack(player, n, hash) => g.ack(player, n, hash),
+            GameMsg::Paint(player, p) => g.paint(player, p),
+            GameMsg::Resync(player) => g.resync(player),
+            GameMsg::Leave(player) => {
+                println!("Player {} left {}", player, name);
+                if !g.leave(player) {
+                    break;
+                }
+            },
+        }
+    }
+
+    // stop taking new players, and send any that were on the way back to the broker
+    rx.close();
+    while let Some(msg) = rx.recv().await {
+        if let GameMsg::Join{client, reply} = msg {
+            let _ = broker.send(BrokerMsg::Join{game: name.clone(), client, reply});
+        }
+    }
+    let _ = broker.send(BrokerMsg::GameClosed(name, gen));
+}
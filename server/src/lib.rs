@@ -0,0 +1,5 @@
+// the broker and game tasks, everything but the websocket side in server.rs
+// kept in a lib so the tasks can be tested without a socket
+pub mod msg;
+pub mod broker;
+pub mod game;
@@ -0,0 +1,33 @@
+// messages passed between the client tasks, the broker, and the game tasks
+// the websocket messages themselves are in stratapaint_protocol
+
+use tokio::sync::{mpsc, oneshot};
+use stratapaint_protocol::{ServerMsg, PaintUpdate, PlayerId};
+
+// what a client task gets back after joining, used to talk to its game
+pub struct GameHandle {
+    pub name: String,
+    pub player: PlayerId,
+    pub tx: mpsc::UnboundedSender<GameMsg>,
+}
+
+pub enum BrokerMsg {
+    Join {
+        game: String,
+        client: mpsc::UnboundedSender<ServerMsg>,
+        reply: oneshot::Sender<GameHandle>,
+    },
+    GameClosed(String, u32), // name, generation
+}
+
+pub enum GameMsg {
+    Join {
+        client: mpsc::UnboundedSender<ServerMsg>,
+        reply: oneshot::Sender<GameHandle>,
+    },
+    Ready(PlayerId),
+    Ack(PlayerId, u32, u64), // netstep, state hash
+    Paint(PlayerId, PaintUpdate),
+    Resync(PlayerId),
+    Leave(PlayerId),
+}
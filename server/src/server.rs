@@ -2,69 +2,155 @@ use warp::Filter;
 use warp::filters::ws::{WebSocket, Message};
 use futures::StreamExt;
 use futures::SinkExt;
+use tokio::sync::{mpsc, oneshot};
+
+use stratapaint_protocol::{ClientMsg, ServerMsg};
+use stratapaint_server::{broker, game};
+use stratapaint_server::msg::{BrokerMsg, GameHandle, GameMsg};
 
 macro_rules! SITEPATH { () => { "./site/" }; }
 const PORT: u16 = 8910;
 
-async fn new_user(wsock: WebSocket) {
+async fn new_user(wsock: WebSocket, broker: mpsc::UnboundedSender<BrokerMsg>) {
     println!("Got New User!");
-    // get the ping, send back a pong, close connection
 
     let (mut wtx, mut wrx) = wsock.split();
-    
-    if let Some(res) = wrx.next().await {
-        match res {
-            Ok(msg) => {
-                if let Ok(s) = msg.to_str() {
-                    println!("Got message from websocket: {}", s);
-                } else {
-                    println!("Got a binary message");
-                }
-            },
+
+    // everything we send goes through this chan, so the game can send to us directly
+    let (ctx, mut crx) = mpsc::unbounded_channel::<ServerMsg>();
+    tokio::spawn(async move {
+        while let Some(msg) = crx.recv().await {
+            if let Err(e) = wtx.send(Message::binary(msg.encode())).await {
+                println!("Could not send message, disconnected: {}", e);
+                break;
+            }
+        }
+    });
+
+    // until we join, the only thing we can do is ping
+    // after that our input gets passed on to the game task
+    let mut game: Option<GameHandle> = None;
+
+    while let Some(res) = wrx.next().await {
+        let msg = match res {
+            Ok(msg) => msg,
             Err(e) => {
                 println!("Got Error from websocket: {}", e);
+                break;
             }
+        };
+
+        if msg.is_close() {
+            break;
         }
-    }
 
+        if msg.is_text() {
+            let _ = ctx.send(ServerMsg::Error(String::from("expected a binary message")));
+            continue;
+        }
+        if !msg.is_binary() {
+            // pings and such from the websocket itself
+            continue;
+        }
+
+        let cmsg = match ClientMsg::decode(msg.as_bytes()) {
+            Ok(m) => m,
+            Err(e) => {
+                println!("Got bad message from websocket: {}", e);
+                let _ = ctx.send(ServerMsg::Error(format!("bad message: {}", e)));
+                continue;
+            }
+        };
 
-    // another option here is to 
-    if let Err(e) = wtx.send(Message::text("PONG")).await {
-        println!("Could not send message, disconnected: {}", e);
+        match cmsg {
+            ClientMsg::Ping => {
+                let _ = ctx.send(ServerMsg::Pong);
+            },
+            ClientMsg::Join(name) => {
+                if game.is_some() {
+                    let _ = ctx.send(ServerMsg::Error(String::from("already in a game")));
+                    continue;
+                }
+
+                let (reply, wait) = oneshot::channel();
+                if broker.send(BrokerMsg::Join{game: name, client: ctx.clone(), reply}).is_err() {
+                    println!("Broker is gone!");
+                    break;
+                }
+
+                match wait.await {
+                    Ok(handle) => {
+                        // the game sends the game info itself
+                        println!("Joined {} as player {}", handle.name, handle.player);
+                        game = Some(handle);
+                    },
+                    Err(_) => {
+                        let _ = ctx.send(ServerMsg::Error(String::from("could not join game")));
+                    },
+                }
+            },
+            ClientMsg::Ready | ClientMsg::Ack(..) | ClientMsg::Paint(_) | ClientMsg::WantResync => {
+                let g = match &game {
+                    Some(g) => g,
+                    None => {
+                        let _ = ctx.send(ServerMsg::Error(String::from("not in a game")));
+                        continue;
+                    },
+                };
+
+                let gmsg = match cmsg {
+                    ClientMsg::Ready => GameMsg::Ready(g.player),
+                    ClientMsg::Ack(n, hash) => GameMsg::Ack(g.player, n, hash),
+                    ClientMsg::Paint(p) => GameMsg::Paint(g.player, p),
+                    ClientMsg::WantResync => GameMsg::Resync(g.player),
+                    _ => unreachable!(),
+                };
+                let _ = g.tx.send(gmsg);
+            },
+        }
     }
 
-    //TODO lots of stuff
-    // example session
-    //  client : server
-    //  -> join game
-    //  <- game info (map inital layout, # players ready, x Steps to 1 NetStep)
-    //  -> ready
-    //  <- NetStep 0 updates (probably empty)
-    //  -> Ack NetStep0
-    //  -> queue Update
-    //  <- NetStep 1 updates (should contain queued updates)
-    //  <- NetStep 2 updates
-
-    // so I need a task per client handling recving data, and send it into a chan to join or create a game
-    // and I will have a main task that can will broker out to games
-    // and I will have a task per game that will pass handle player input and send out updates
+    if let Some(g) = game {
+        let _ = g.tx.send(GameMsg::Leave(g.player));
+    }
+    println!("User disconnected");
 }
 
 #[tokio::main]
 async fn main() {
+    // an optional png map to play every game on, otherwise each game makes up its own
+    let map = match std::env::args().nth(1) {
+        Some(path) => match game::load_map(&path) {
+            Ok(m) => {
+                println!("Playing on map {}", path);
+                Some(m)
+            },
+            Err(e) => {
+                println!("Could not load map {}: {}", path, e);
+                return;
+            },
+        },
+        None => None,
+    };
+
+    // start task for game broker
+    // (which will start tasks for each game)
+    let (btx, brx) = mpsc::unbounded_channel();
+    tokio::spawn(broker::run(brx, btx.clone(), map));
+
+    // pass chan reference to each wspath client
+    let btx = warp::any().map(move || btx.clone());
+
     let wspath = warp::path("con")
         .and(warp::ws())
-        .map(|ws: warp::ws::Ws| {
-            ws.on_upgrade(new_user)
+        .and(btx)
+        .map(|ws: warp::ws::Ws, btx: mpsc::UnboundedSender<BrokerMsg>| {
+            ws.on_upgrade(move |wsock| new_user(wsock, btx))
         });
     let site = warp::fs::dir(SITEPATH!());
 
     let routes = wspath.or(site);
 
-    //TODO start task for game broker
-    // (which will start tasks for each game)
-    // pass chan reference to each wspath client
-
     println!("Serving on {}", PORT);
     warp::serve(routes).run(([0,0,0,0], PORT)).await;
 }
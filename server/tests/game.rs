@@ -0,0 +1,45 @@
+// a game task closes and tells the broker once its last player is gone, however they went
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+use stratapaint_server::game;
+use stratapaint_server::msg::{BrokerMsg, GameMsg};
+
+async fn closed(brx: &mut mpsc::UnboundedReceiver<BrokerMsg>) -> Option<(String, u32)> {
+    match tokio::time::timeout(Duration::from_secs(10), brx.recv()).await {
+        Ok(Some(BrokerMsg::GameClosed(name, gen))) => Some((name, gen)),
+        _ => None,
+    }
+}
+
+#[tokio::test]
+async fn closes_when_the_last_player_leaves() {
+    let (btx, mut brx) = mpsc::unbounded_channel();
+    let (gtx, grx) = mpsc::unbounded_channel();
+    let task = tokio::spawn(game::run("a".to_string(), 1, None, grx, btx));
+
+    let (client, _crx) = mpsc::unbounded_channel();
+    let (reply, wait) = oneshot::channel();
+    assert!(gtx.send(GameMsg::Join{client, reply}).is_ok());
+    let handle = wait.await.expect("never got let in");
+    assert!(handle.tx.send(GameMsg::Leave(handle.player)).is_ok());
+
+    assert_eq!(closed(&mut brx).await, Some(("a".to_string(), 1)));
+    task.await.unwrap();
+}
+
+#[tokio::test]
+async fn closes_when_the_only_joiner_gives_up() {
+    let (btx, mut brx) = mpsc::unbounded_channel();
+    let (gtx, grx) = mpsc::unbounded_channel();
+    let task = tokio::spawn(game::run("b".to_string(), 2, None, grx, btx));
+
+    // they hung up before the game got back to them
+    let (client, _crx) = mpsc::unbounded_channel();
+    let (reply, wait) = oneshot::channel();
+    drop(wait);
+    assert!(gtx.send(GameMsg::Join{client, reply}).is_ok());
+
+    assert_eq!(closed(&mut brx).await, Some(("b".to_string(), 2)));
+    task.await.unwrap();
+}
@@ -5,17 +5,62 @@ var prevts = 0;
 var avdt = [];
 var prevtick = 0;
 var avtick = [];
-var mapimg = undefined;
+var wasmmem = undefined;
+var mapw = 0;
+var maph = 0;
 var can = document.getElementById(canid);
 var ctx = can.getContext("2d");
 ctx.imageSmoothingEnabled = false;
 var can2 = undefined;
 var ctx2 = undefined;
 
+var ws = undefined;
+
 var dispscale=1.0;
 var dispxoff = 0;
 var dispyoff = 0;
 
+// what the mouse paints, see paint_stroke
+var paintkind = 0;
+var paintdir = 0;
+var paintrad = 6;
+var lastpaint = undefined; // where the mouse was last painted while dragging
+
+// put a wasm buffer on the screen
+// the views have to be made again each time, the buffers move around and wasm memory can grow
+function drawbuf(buf) {
+    if (buf == 0) {
+        return;
+    }
+    var img = new ImageData(new Uint8ClampedArray(wasmmem.buffer, buf, mapw * maph * 4), mapw, maph);
+    ctx2.putImageData(img, 0, 0);
+    ctx.drawImage(can2, 0, 0);
+}
+
+// paint where the mouse is, in map coords
+// a dot to start, then a line from wherever it was last so fast drags don't leave gaps
+// or with shift held, fill out to the walls
+function dopaint(evt, start) {
+    var x = Math.floor((evt.offsetX - dispxoff) / dispscale);
+    var y = Math.floor((evt.offsetY - dispyoff) / dispscale);
+    if (x < 0 || y < 0) {
+        return;
+    }
+
+    var sent;
+    if (start && evt.shiftKey) {
+        sent = paint_fill(x, y, paintkind, paintdir);
+    } else if (start || lastpaint === undefined) {
+        sent = paint_stroke(x, y, paintrad, paintkind, paintdir);
+    } else {
+        sent = paint_line(new Uint32Array([lastpaint[0], lastpaint[1], x, y]), paintrad * 2 + 1, paintkind, paintdir);
+    }
+    lastpaint = [x, y];
+    if (sent) {
+        flush();
+    }
+}
+
 function dodraw(ts) {
     // draw the game
     var dt = ts - prevts;
@@ -39,23 +84,32 @@ function dodraw(ts) {
     ctx.clearRect(0.0, 0.0, can.width, can.height);
     ctx.restore();
 
-    // put the image data on our separate canvas
-    ctx2.putImageData(mapimg, 0, 0);
-    ctx.drawImage(can2, 0, 0);
+    // the map, then our team's paint, then our paint still on the way to the server
+    drawbuf(get_buf(-1));
+    drawbuf(get_buf(local_team()));
+    drawbuf(get_paint_buf());
 
     // let the engine update it's stuff
     draw(dt);
 
-    // user painting
-    //TODO
-
     requestAnimationFrame(dodraw);
 }
 
+function flush() {
+    // send anything the engine has queued up for the server
+    var msg;
+    while ((msg = net_send()) !== undefined) {
+        ws.send(msg);
+    }
+}
+
 function dotick() {
     var ts = performance.now();
     
-    tick();
+    // if we are waiting on a netstep this does nothing
+    if (tick()) {
+        flush();
+    }
 
     var dt = performance.now() - ts;
     prevtick = dt;
@@ -68,42 +122,45 @@ function dotick() {
 function main(mem) {
     console.log("Game loading...");
 
-    let ws = new WebSocket("ws://" + location.host + "/con");
+    // the game gets started once the server sends us the game info
+    connect(canid, "default");
+
+    ws = new WebSocket("ws://" + location.host + "/con");
+    ws.binaryType = "arraybuffer";
     ws.onopen = function() {
-        ws.send("PING");
+        flush();
     };
     ws.onmessage = function(msg) {
-        //console.log(msg);
+        if (net_recv(new Uint8Array(msg.data))) {
+            start(mem);
+        }
+        flush();
     };
     ws.onclose = function() {
-        //console.log("Connection closed!");
+        console.log("Connection closed!");
     };
+}
+
+function start(mem) {
+    var tick_step = game_tick_step();
+    mapw = game_width();
+    maph = game_height();
+    wasmmem = mem;
 
     // set up canvas zooming/ moving
     //TODO
 
-    var tick_step = 100;
-    var width = 800;
-    var height = 800;
-
-    init_game(canid, width, height, 0x100, tick_step, 0);
-
-    // set up a ImageData for the map
-    var buf = get_buf(-1);
-    if (buf == 0) {
+    // make sure the map is there
+    if (get_buf(-1) == 0) {
         console.log("Got null Map Buffer");
         return;
     }
 
-    // set up needed image buffers
-    var len = width * height * 4;
-    var mapbuf = new Uint8ClampedArray(mem.buffer, buf, len);
-    mapimg = new ImageData(mapbuf, width, height);
-
+    // layers go on here first, then onto the real canvas
     can2 = document.createElement('canvas');
     can2.id = "can2";
-    can2.width = width;
-    can2.height = height;
+    can2.width = mapw;
+    can2.height = maph;
     ctx2 = can2.getContext("2d");
 
     // set up camera transform callbacks
@@ -116,6 +173,24 @@ function main(mem) {
     }
     window.onkeydown = function(evt) {
         console.log(evt);
+
+        // 1 to 4 pick the paint kind and 5 erases, q and e turn the direction for direction paint
+        switch (evt.key) {
+            case "1":
+            case "2":
+            case "3":
+            case "4":
+            case "5":
+                paintkind = parseInt(evt.key) - 1;
+                return false;
+            case "q":
+                paintdir = (paintdir + 224) % 256;
+                return false;
+            case "e":
+                paintdir = (paintdir + 32) % 256;
+                return false;
+        }
+
         var camchanged = false;
         switch (evt.key) {
             case "ArrowDown":
@@ -146,7 +221,22 @@ function main(mem) {
     }
 
     // set up user painting callbacks
-    //TODO
+    // drag to paint
+    can.onmousedown = function(evt) {
+        dopaint(evt, true);
+        if (evt.shiftKey) {
+            lastpaint = undefined;
+        }
+        return false;
+    }
+    can.onmousemove = function(evt) {
+        if (lastpaint !== undefined) {
+            dopaint(evt, false);
+        }
+    }
+    window.onmouseup = function() {
+        lastpaint = undefined;
+    }
 
     // start drawing
     requestAnimationFrame(dodraw);
@@ -166,7 +256,7 @@ function main(mem) {
 }
 
 // first init webasm and import the symbols we need
-import init, { adj_dis, init_game, tick, draw, get_buf } from './clientwasm.js';
+import init, { adj_dis, tick, draw, get_buf, get_paint_buf, local_team, paint_stroke, paint_line, paint_fill, connect, net_recv, net_send, game_width, game_height, game_tick_step } from './clientwasm.js';
 (async function() {
     var wasm = await init();
     //console.log(wasm);